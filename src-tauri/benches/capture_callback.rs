@@ -0,0 +1,127 @@
+// Callback latency of the capture path: the old shared Mutex<Vec<f32>> versus the SPSC ring.
+//
+// Each callback gets 10ms of 48kHz stereo, as from a typical input device. The mutex variant
+// replays the old callback: downmix and resample into fresh Vecs, then lock the shared buffer.
+// The ring variant calls the shipped `CaptureSink::push_interleaved` while `SourceReader`
+// drains it on a consumer thread. Both run with a poller that clones the stored buffer the
+// way the frontend used to poll get_audio_stats, on top of ten minutes of recorded audio.
+//
+//     cargo bench --bench capture_callback
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use notlok_lib::bench::channel;
+
+const CALLBACKS: usize = 2000;
+const DEVICE_RATE: u32 = 48000;
+const DEVICE_CHANNELS: u16 = 2;
+const CALLBACK_FRAMES: usize = 480; // 10ms at 48kHz
+const RECORDED_SAMPLES: usize = 16000 * 60 * 10;
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+fn spawn_poller(buffer: Arc<Mutex<Vec<f32>>>, running: Arc<AtomicBool>) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        while running.load(Ordering::Relaxed) {
+            let copy = buffer.lock().unwrap().clone();
+            std::hint::black_box(copy);
+            thread::sleep(POLL_INTERVAL);
+        }
+    })
+}
+
+fn callback_data() -> Vec<f32> {
+    (0..CALLBACK_FRAMES * DEVICE_CHANNELS as usize).map(|i| (i as f32 * 0.01).sin()).collect()
+}
+
+// The callback before the ring, allocation and all
+fn old_callback(data: &[f32], buffer: &Mutex<Vec<f32>>) {
+    let channels = DEVICE_CHANNELS as usize;
+    let mono: Vec<f32> = data.chunks(channels).map(|c| c.iter().sum::<f32>() / channels as f32).collect();
+    let ratio = DEVICE_RATE as f64 / 16000.0;
+    let len = (mono.len() as f64 / ratio) as usize;
+    let mut resampled = Vec::with_capacity(len);
+    for i in 0..len {
+        let position = i as f64 * ratio;
+        let index = position as usize;
+        let frac = (position - index as f64) as f32;
+        if index + 1 < mono.len() {
+            resampled.push(mono[index] * (1.0 - frac) + mono[index + 1] * frac);
+        } else {
+            resampled.push(mono[index]);
+        }
+    }
+    buffer.lock().unwrap().extend_from_slice(&resampled);
+}
+
+fn mutex_path() -> Vec<Duration> {
+    let buffer = Arc::new(Mutex::new(vec![0.0f32; RECORDED_SAMPLES]));
+    let running = Arc::new(AtomicBool::new(true));
+    let poller = spawn_poller(buffer.clone(), running.clone());
+
+    let data = callback_data();
+    let mut latencies = Vec::with_capacity(CALLBACKS);
+    for _ in 0..CALLBACKS {
+        let start = Instant::now();
+        old_callback(&data, &buffer);
+        latencies.push(start.elapsed());
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    running.store(false, Ordering::Relaxed);
+    poller.join().unwrap();
+    latencies
+}
+
+fn ring_path() -> Vec<Duration> {
+    let buffer = Arc::new(Mutex::new(vec![0.0f32; RECORDED_SAMPLES]));
+    let running = Arc::new(AtomicBool::new(true));
+    let poller = spawn_poller(buffer.clone(), running.clone());
+
+    let (mut sink, mut reader) = channel();
+    let consumer_running = running.clone();
+    let storage = buffer.clone();
+    let drain = thread::spawn(move || {
+        let mut scratch = Vec::new();
+        while consumer_running.load(Ordering::Relaxed) {
+            scratch.clear();
+            reader.read_into(&mut scratch);
+            storage.lock().unwrap().extend_from_slice(&scratch);
+            thread::sleep(Duration::from_millis(10));
+        }
+        reader.dropped()
+    });
+
+    let data = callback_data();
+    let mut latencies = Vec::with_capacity(CALLBACKS);
+    for _ in 0..CALLBACKS {
+        let start = Instant::now();
+        sink.push_interleaved(&data, DEVICE_CHANNELS, DEVICE_RATE);
+        latencies.push(start.elapsed());
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    running.store(false, Ordering::Relaxed);
+    poller.join().unwrap();
+    let dropped = drain.join().unwrap();
+    assert_eq!(dropped, 0, "the consumer fell behind");
+    latencies
+}
+
+fn report(name: &str, mut latencies: Vec<Duration>) {
+    latencies.sort();
+    let percentile = |p: f64| latencies[((latencies.len() - 1) as f64 * p) as usize];
+    println!(
+        "{:<12} p50 {:>10.2?}  p99 {:>10.2?}  max {:>10.2?}",
+        name,
+        percentile(0.50),
+        percentile(0.99),
+        latencies[latencies.len() - 1]
+    );
+}
+
+fn main() {
+    report("mutex", mutex_path());
+    report("spsc ring", ring_path());
+}
@@ -0,0 +1,797 @@
+// AI providers
+// Reports come from a cloud API (OpenAI, Anthropic, Gemini or anything compatible with the
+// first two) or a local model server. A provider only knows its own request and response
+// formats; sending, retrying on rate limits and server errors, streaming and counting
+// tokens happen once in `AiClient` for all of them.
+
+use std::time::Duration;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::error::NotlokError;
+use crate::jobs::CancelToken;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// How long a response may keep us waiting, for its start and then between chunks. Local
+// models on a CPU can take minutes to read a long prompt.
+#[cfg(not(test))]
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(300);
+#[cfg(test)]
+const RESPONSE_TIMEOUT: Duration = Duration::from_millis(300);
+#[cfg(not(test))]
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+#[cfg(test)]
+const FIRST_RETRY_DELAY: Duration = Duration::from_millis(10);
+// Longer waits asked for by a server fail instead, the user is watching a spinner
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    #[default]
+    Local,
+    #[serde(rename = "openai")]
+    OpenAi,
+    Anthropic,
+    Gemini,
+}
+
+impl ProviderKind {
+    /// The keychain entry holding the API key, local servers don't need one
+    pub fn secret_name(self) -> Option<&'static str> {
+        match self {
+            ProviderKind::Local => None,
+            ProviderKind::OpenAi => Some("openai-api-key"),
+            ProviderKind::Anthropic => Some("anthropic-api-key"),
+            ProviderKind::Gemini => Some("gemini-api-key"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Endpoint {
+    pub base_url: String,      // up to and including the API version, like .../v1
+    pub model: Option<String>, // None lets a local server choose, cloud APIs need one
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AiConfig {
+    pub default_provider: ProviderKind,
+    pub local: Endpoint, // llama.cpp server, Ollama or anything else OpenAI-compatible
+    pub openai: Endpoint,
+    pub anthropic: Endpoint,
+    pub gemini: Endpoint,
+    pub max_retries: u32,
+    pub max_output_tokens: u32, // Anthropic requires a limit, the others get it too
+}
+
+impl Default for AiConfig {
+    fn default() -> Self {
+        let endpoint = |base_url: &str, model: Option<&str>| Endpoint {
+            base_url: base_url.to_string(),
+            model: model.map(String::from),
+        };
+        Self {
+            default_provider: ProviderKind::Local,
+            local: endpoint("http://localhost:11434/v1", None),
+            openai: endpoint("https://api.openai.com/v1", Some("gpt-4o-mini")),
+            anthropic: endpoint("https://api.anthropic.com/v1", Some("claude-3-5-haiku-latest")),
+            gemini: endpoint("https://generativelanguage.googleapis.com/v1beta", Some("gemini-2.0-flash")),
+            max_retries: 3,
+            max_output_tokens: 4096,
+        }
+    }
+}
+
+impl AiConfig {
+    pub fn endpoint(&self, kind: ProviderKind) -> &Endpoint {
+        match kind {
+            ProviderKind::Local => &self.local,
+            ProviderKind::OpenAi => &self.openai,
+            ProviderKind::Anthropic => &self.anthropic,
+            ProviderKind::Gemini => &self.gemini,
+        }
+    }
+}
+
+/// Token counts as reported by the provider, or estimated from the text when it doesn't
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub estimated: bool,
+}
+
+impl TokenUsage {
+    pub fn add(&mut self, other: TokenUsage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.estimated |= other.estimated;
+    }
+
+    // Streamed counts are running totals, some providers repeat them in later events
+    fn merge(&mut self, other: TokenUsage) {
+        self.prompt_tokens = self.prompt_tokens.max(other.prompt_tokens);
+        self.completion_tokens = self.completion_tokens.max(other.completion_tokens);
+    }
+}
+
+/// Roughly 3.5 characters per token for Turkish and English text
+pub fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64 * 2).div_ceil(7)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Completion {
+    pub text: String,
+    pub usage: TokenUsage,
+}
+
+/// What one server-sent event carried
+#[derive(Default)]
+pub struct StreamEvent {
+    pub text: Option<String>,
+    pub usage: Option<TokenUsage>,
+    pub done: bool,
+}
+
+pub trait AiProvider: Send + Sync {
+    fn kind(&self) -> ProviderKind;
+    fn base_url(&self) -> &str;
+    /// The request for one prompt, streamed as server-sent events if the API can
+    fn request(&self, http: &reqwest::Client, prompt: &str, temperature: f32) -> reqwest::RequestBuilder;
+    /// A whole response, for servers that answered without streaming
+    fn parse_response(&self, body: &Value) -> Option<Completion>;
+    /// The data of one server-sent event
+    fn parse_event(&self, data: &Value) -> StreamEvent;
+}
+
+fn endpoint_url(base_url: &str, path: &str) -> String {
+    format!("{}/{}", base_url.trim_end_matches('/'), path)
+}
+
+fn usage(prompt: &Value, completion: &Value) -> Option<TokenUsage> {
+    let (prompt, completion) = (prompt.as_u64(), completion.as_u64());
+    (prompt.is_some() || completion.is_some()).then(|| TokenUsage {
+        prompt_tokens: prompt.unwrap_or_default(),
+        completion_tokens: completion.unwrap_or_default(),
+        estimated: false,
+    })
+}
+
+/// OpenAI's chat completions API, which llama.cpp, Ollama, vLLM and others also serve
+pub struct OpenAiCompatible {
+    pub kind: ProviderKind,
+    pub endpoint: Endpoint,
+    pub api_key: Option<String>,
+    pub max_output_tokens: u32,
+}
+
+impl AiProvider for OpenAiCompatible {
+    fn kind(&self) -> ProviderKind {
+        self.kind
+    }
+
+    fn base_url(&self) -> &str {
+        &self.endpoint.base_url
+    }
+
+    fn request(&self, http: &reqwest::Client, prompt: &str, temperature: f32) -> reqwest::RequestBuilder {
+        let mut body = json!({
+            "messages": [{ "role": "user", "content": prompt }],
+            "temperature": temperature,
+            "max_tokens": self.max_output_tokens,
+            "stream": true,
+        });
+        if let Some(model) = &self.endpoint.model {
+            body["model"] = json!(model);
+        }
+        // Local servers differ in what they accept, only OpenAI itself is asked for usage
+        if self.kind == ProviderKind::OpenAi {
+            body["stream_options"] = json!({ "include_usage": true });
+        }
+        let request = http.post(endpoint_url(&self.endpoint.base_url, "chat/completions")).json(&body);
+        match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+
+    fn parse_response(&self, body: &Value) -> Option<Completion> {
+        let text = body["choices"][0]["message"]["content"].as_str()?;
+        Some(Completion {
+            text: text.to_string(),
+            usage: usage(&body["usage"]["prompt_tokens"], &body["usage"]["completion_tokens"]).unwrap_or_default(),
+        })
+    }
+
+    fn parse_event(&self, data: &Value) -> StreamEvent {
+        StreamEvent {
+            text: data["choices"][0]["delta"]["content"].as_str().map(String::from),
+            usage: usage(&data["usage"]["prompt_tokens"], &data["usage"]["completion_tokens"]),
+            done: false,
+        }
+    }
+}
+
+/// Anthropic's messages API
+pub struct Anthropic {
+    pub endpoint: Endpoint,
+    pub api_key: String,
+    pub max_output_tokens: u32,
+}
+
+impl AiProvider for Anthropic {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Anthropic
+    }
+
+    fn base_url(&self) -> &str {
+        &self.endpoint.base_url
+    }
+
+    fn request(&self, http: &reqwest::Client, prompt: &str, temperature: f32) -> reqwest::RequestBuilder {
+        http.post(endpoint_url(&self.endpoint.base_url, "messages"))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&json!({
+                "model": self.endpoint.model.as_deref().unwrap_or_default(),
+                "max_tokens": self.max_output_tokens,
+                "temperature": temperature,
+                "messages": [{ "role": "user", "content": prompt }],
+                "stream": true,
+            }))
+    }
+
+    fn parse_response(&self, body: &Value) -> Option<Completion> {
+        let text: String = body["content"].as_array()?
+            .iter()
+            .filter_map(|block| block["text"].as_str())
+            .collect();
+        Some(Completion {
+            text,
+            usage: usage(&body["usage"]["input_tokens"], &body["usage"]["output_tokens"]).unwrap_or_default(),
+        })
+    }
+
+    fn parse_event(&self, data: &Value) -> StreamEvent {
+        match data["type"].as_str() {
+            Some("message_start") => StreamEvent {
+                usage: usage(&data["message"]["usage"]["input_tokens"], &data["message"]["usage"]["output_tokens"]),
+                ..Default::default()
+            },
+            Some("content_block_delta") => StreamEvent {
+                text: data["delta"]["text"].as_str().map(String::from),
+                ..Default::default()
+            },
+            Some("message_delta") => StreamEvent {
+                usage: usage(&Value::Null, &data["usage"]["output_tokens"]),
+                ..Default::default()
+            },
+            Some("message_stop") => StreamEvent { done: true, ..Default::default() },
+            _ => StreamEvent::default(),
+        }
+    }
+}
+
+/// Google's Gemini API
+pub struct Gemini {
+    pub endpoint: Endpoint,
+    pub api_key: String,
+    pub max_output_tokens: u32,
+}
+
+impl AiProvider for Gemini {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Gemini
+    }
+
+    fn base_url(&self) -> &str {
+        &self.endpoint.base_url
+    }
+
+    fn request(&self, http: &reqwest::Client, prompt: &str, temperature: f32) -> reqwest::RequestBuilder {
+        let model = self.endpoint.model.as_deref().unwrap_or_default();
+        let path = format!("models/{}:streamGenerateContent?alt=sse", model);
+        http.post(endpoint_url(&self.endpoint.base_url, &path))
+            .header("x-goog-api-key", &self.api_key)
+            .json(&json!({
+                "contents": [{ "parts": [{ "text": prompt }] }],
+                "generationConfig": {
+                    "temperature": temperature,
+                    "maxOutputTokens": self.max_output_tokens,
+                },
+            }))
+    }
+
+    fn parse_response(&self, body: &Value) -> Option<Completion> {
+        let event = self.parse_event(body);
+        Some(Completion {
+            text: event.text?,
+            usage: event.usage.unwrap_or_default(),
+        })
+    }
+
+    fn parse_event(&self, data: &Value) -> StreamEvent {
+        let text = data["candidates"][0]["content"]["parts"].as_array().map(|parts| {
+            parts.iter().filter_map(|part| part["text"].as_str()).collect::<String>()
+        });
+        StreamEvent {
+            text,
+            usage: usage(&data["usageMetadata"]["promptTokenCount"], &data["usageMetadata"]["candidatesTokenCount"]),
+            done: false,
+        }
+    }
+}
+
+/// The provider for `kind`. Cloud providers need their API key.
+pub fn provider(kind: ProviderKind, config: &AiConfig, api_key: Option<String>) -> Result<Box<dyn AiProvider>, NotlokError> {
+    let endpoint = config.endpoint(kind).clone();
+    if kind != ProviderKind::Local && endpoint.model.is_none() {
+        return Err(NotlokError::InvalidConfig { field: "model".to_string() });
+    }
+    let required_key = || api_key.clone().ok_or(NotlokError::MissingApiKey { provider: kind });
+    let max_output_tokens = config.max_output_tokens;
+    Ok(match kind {
+        ProviderKind::Local | ProviderKind::OpenAi => Box::new(OpenAiCompatible {
+            kind,
+            endpoint,
+            api_key: if kind == ProviderKind::OpenAi { Some(required_key()?) } else { api_key },
+            max_output_tokens,
+        }),
+        ProviderKind::Anthropic => Box::new(Anthropic { endpoint, api_key: required_key()?, max_output_tokens }),
+        ProviderKind::Gemini => Box::new(Gemini { endpoint, api_key: required_key()?, max_output_tokens }),
+    })
+}
+
+pub struct AiClient {
+    http: reqwest::Client,
+    provider: Box<dyn AiProvider>,
+    max_retries: u32,
+    temperature: f32,
+}
+
+impl AiClient {
+    pub fn new(provider: Box<dyn AiProvider>, max_retries: u32, temperature: f32) -> Result<Self, NotlokError> {
+        let http = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()
+            .map_err(|e| NotlokError::internal(e.to_string()))?;
+        Ok(Self { http, provider, max_retries, temperature })
+    }
+
+    fn failed(&self, message: impl std::fmt::Display) -> NotlokError {
+        NotlokError::AiRequestFailed {
+            provider: self.provider.kind(),
+            message: message.to_string(),
+        }
+    }
+
+    fn timed_out(&self) -> NotlokError {
+        self.failed(format!("No response for {} seconds", RESPONSE_TIMEOUT.as_secs()))
+    }
+
+    /// Sends one prompt, `on_token` gets the answer as it streams in. Rate limits, server
+    /// errors and timeouts are retried with backoff until the answer starts arriving.
+    pub async fn complete(&self, prompt: &str, cancel: &CancelToken, on_token: &mut dyn FnMut(&str)) -> Result<Completion, NotlokError> {
+        let response = self.send(prompt, cancel).await?;
+
+        // Servers that don't stream answer with a single completion
+        let streamed = response.headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+        if !streamed {
+            let body: Value = tokio::time::timeout(RESPONSE_TIMEOUT, response.json())
+                .await
+                .map_err(|_| self.timed_out())?
+                .map_err(|e| self.failed(e))?;
+            let mut completion = self.provider.parse_response(&body)
+                .ok_or_else(|| self.failed(format!("Unexpected response: {}", body)))?;
+            self.fill_usage(&mut completion.usage, prompt, &completion.text);
+            on_token(&completion.text);
+            return Ok(completion);
+        }
+
+        let mut completion = Completion::default();
+        let mut buffer = Vec::new();
+        let mut stream = response.bytes_stream();
+        'stream: loop {
+            // reqwest 0.11 has no read timeout, a stalled stream is caught here
+            let chunk = match tokio::time::timeout(RESPONSE_TIMEOUT, stream.next()).await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break,
+                Err(_) => return Err(self.timed_out()),
+            };
+            cancel.check()?;
+            buffer.extend_from_slice(&chunk.map_err(|e| self.failed(e))?);
+            // Server-sent events, the payload is on `data:` lines
+            while let Some(newline) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=newline).collect();
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim().strip_prefix("data:").map(str::trim) else {
+                    continue;
+                };
+                if data == "[DONE]" {
+                    break 'stream;
+                }
+                let data: Value = serde_json::from_str(data).map_err(|e| self.failed(format!("Unexpected event: {}", e)))?;
+                if let Some(error) = data.get("error") {
+                    return Err(self.failed(error["message"].as_str().map_or_else(|| error.to_string(), String::from)));
+                }
+                let event = self.provider.parse_event(&data);
+                if let Some(text) = event.text.filter(|t| !t.is_empty()) {
+                    on_token(&text);
+                    completion.text.push_str(&text);
+                }
+                if let Some(usage) = event.usage {
+                    completion.usage.merge(usage);
+                }
+                if event.done {
+                    break 'stream;
+                }
+            }
+        }
+        self.fill_usage(&mut completion.usage, prompt, &completion.text);
+        Ok(completion)
+    }
+
+    async fn send(&self, prompt: &str, cancel: &CancelToken) -> Result<reqwest::Response, NotlokError> {
+        let mut attempt = 0;
+        loop {
+            cancel.check()?;
+            let request = self.provider.request(&self.http, prompt, self.temperature).send();
+            let retry_after = match tokio::time::timeout(RESPONSE_TIMEOUT, request).await {
+                Err(_) if attempt < self.max_retries => None,
+                Err(_) => return Err(self.timed_out()),
+                Ok(Ok(response)) if response.status().is_success() => return Ok(response),
+                Ok(Ok(response)) => {
+                    let status = response.status();
+                    let retryable = status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+                    let retry_after = retry_after(&response);
+                    if !retryable || attempt >= self.max_retries || retry_after.is_some_and(|d| d > MAX_RETRY_DELAY) {
+                        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                            return Err(NotlokError::RateLimited { provider: self.provider.kind() });
+                        }
+                        let body = response.text().await.unwrap_or_default();
+                        return Err(self.failed(format!("{}: {}", status, body.trim())));
+                    }
+                    retry_after
+                }
+                // Nothing is listening, retrying won't change that
+                Ok(Err(e)) if e.is_connect() => {
+                    return Err(NotlokError::LlmUnavailable { url: self.provider.base_url().to_string() });
+                }
+                Ok(Err(e)) => return Err(self.failed(e)),
+            };
+
+            let backoff = FIRST_RETRY_DELAY * 2u32.pow(attempt);
+            tokio::time::sleep(retry_after.unwrap_or(backoff).min(MAX_RETRY_DELAY)).await;
+            attempt += 1;
+        }
+    }
+
+    fn fill_usage(&self, usage: &mut TokenUsage, prompt: &str, answer: &str) {
+        if usage.prompt_tokens == 0 {
+            usage.prompt_tokens = estimate_tokens(prompt);
+            usage.estimated = true;
+        }
+        if usage.completion_tokens == 0 && !answer.is_empty() {
+            usage.completion_tokens = estimate_tokens(answer);
+            usage.estimated = true;
+        }
+    }
+}
+
+// Only the delay-in-seconds form, HTTP dates fall back to the backoff
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    response.headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client(kind: ProviderKind, server: &MockServer, max_retries: u32) -> AiClient {
+        let mut config = AiConfig::default();
+        let base_url = format!("{}/v1", server.uri());
+        match kind {
+            ProviderKind::Local => config.local.base_url = base_url,
+            ProviderKind::OpenAi => config.openai.base_url = base_url,
+            ProviderKind::Anthropic => config.anthropic.base_url = base_url,
+            ProviderKind::Gemini => config.gemini.base_url = base_url,
+        }
+        AiClient::new(provider(kind, &config, Some("test-key".to_string())).unwrap(), max_retries, 0.2).unwrap()
+    }
+
+    fn answer(text: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{ "message": { "content": text } }],
+            "usage": { "prompt_tokens": 12, "completion_tokens": 3 },
+        }))
+    }
+
+    fn events(events: &[&str]) -> ResponseTemplate {
+        let body: String = events.iter().map(|e| format!("data: {}\n\n", e)).collect();
+        ResponseTemplate::new(200).set_body_raw(body, "text/event-stream")
+    }
+
+    async fn complete(client: &AiClient, prompt: &str) -> (Result<Completion, NotlokError>, Vec<String>) {
+        let mut tokens = Vec::new();
+        let result = client.complete(prompt, &CancelToken::default(), &mut |t| tokens.push(t.to_string())).await;
+        (result, tokens)
+    }
+
+    async fn request_count(server: &MockServer) -> usize {
+        server.received_requests().await.unwrap().len()
+    }
+
+    #[tokio::test]
+    async fn rate_limit_waits_for_retry_after() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "1"))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST")).respond_with(answer("ok")).mount(&server).await;
+
+        let start = Instant::now();
+        let (result, _) = complete(&client(ProviderKind::OpenAi, &server, 3), "Hi").await;
+        assert_eq!(result.unwrap().text, "ok");
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert_eq!(request_count(&server).await, 2);
+    }
+
+    #[tokio::test]
+    async fn server_errors_back_off_then_succeed() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST")).respond_with(answer("ok")).mount(&server).await;
+
+        let start = Instant::now();
+        let (result, _) = complete(&client(ProviderKind::OpenAi, &server, 3), "Hi").await;
+        assert_eq!(result.unwrap().text, "ok");
+        // 1x then 2x the first delay
+        assert!(start.elapsed() >= FIRST_RETRY_DELAY * 3);
+        assert_eq!(request_count(&server).await, 3);
+    }
+
+    #[tokio::test]
+    async fn rate_limited_once_retries_run_out() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(429)).mount(&server).await;
+
+        let (result, _) = complete(&client(ProviderKind::Anthropic, &server, 2), "Hi").await;
+        assert!(matches!(result, Err(NotlokError::RateLimited { provider: ProviderKind::Anthropic })));
+        assert_eq!(request_count(&server).await, 3);
+    }
+
+    #[tokio::test]
+    async fn retry_after_beyond_the_limit_fails_at_once() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "3600"))
+            .mount(&server)
+            .await;
+
+        let (result, _) = complete(&client(ProviderKind::OpenAi, &server, 3), "Hi").await;
+        assert!(matches!(result, Err(NotlokError::RateLimited { provider: ProviderKind::OpenAi })));
+        assert_eq!(request_count(&server).await, 1);
+    }
+
+    #[tokio::test]
+    async fn server_error_after_retries_reports_the_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(500).set_body_string("overloaded")).mount(&server).await;
+
+        let (result, _) = complete(&client(ProviderKind::OpenAi, &server, 1), "Hi").await;
+        match result {
+            Err(NotlokError::AiRequestFailed { provider: ProviderKind::OpenAi, message }) => {
+                assert!(message.contains("500") && message.contains("overloaded"), "{}", message);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(request_count(&server).await, 2);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(401)).mount(&server).await;
+
+        let (result, _) = complete(&client(ProviderKind::OpenAi, &server, 3), "Hi").await;
+        assert!(matches!(result, Err(NotlokError::AiRequestFailed { .. })));
+        assert_eq!(request_count(&server).await, 1);
+    }
+
+    #[tokio::test]
+    async fn slow_responses_time_out_and_are_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(answer("late").set_delay(RESPONSE_TIMEOUT * 2))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST")).respond_with(answer("ok")).mount(&server).await;
+
+        let (result, _) = complete(&client(ProviderKind::OpenAi, &server, 1), "Hi").await;
+        assert_eq!(result.unwrap().text, "ok");
+
+        let (result, _) = complete(&client(ProviderKind::OpenAi, &server, 0), "Hi").await;
+        assert_eq!(result.unwrap().text, "ok"); // the slow mock is used up
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).respond_with(answer("late").set_delay(RESPONSE_TIMEOUT * 2)).mount(&server).await;
+        let (result, _) = complete(&client(ProviderKind::OpenAi, &server, 0), "Hi").await;
+        assert!(matches!(result, Err(NotlokError::AiRequestFailed { .. })));
+    }
+
+    #[tokio::test]
+    async fn nothing_listening_is_unavailable() {
+        // wiremock keeps stopped servers listening for reuse, so take a port nobody listens on
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let mut config = AiConfig::default();
+        config.local.base_url = format!("http://127.0.0.1:{}/v1", port);
+        let client = AiClient::new(provider(ProviderKind::Local, &config, None).unwrap(), 3, 0.2).unwrap();
+        let (result, _) = complete(&client, "Hi").await;
+        assert!(matches!(result, Err(NotlokError::LlmUnavailable { .. })));
+    }
+
+    #[tokio::test]
+    async fn parses_openai_stream() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(header("authorization", "Bearer test-key"))
+            .respond_with(events(&[
+                r#"{"choices":[{"delta":{"role":"assistant","content":""}}]}"#,
+                r#"{"choices":[{"delta":{"content":"Merhaba"}}]}"#,
+                r#"{"choices":[{"delta":{"content":" dünya"}}]}"#,
+                r#"{"choices":[],"usage":{"prompt_tokens":9,"completion_tokens":4}}"#,
+                "[DONE]",
+            ]))
+            .mount(&server)
+            .await;
+
+        let (result, tokens) = complete(&client(ProviderKind::OpenAi, &server, 0), "Hi").await;
+        let completion = result.unwrap();
+        assert_eq!(tokens, vec!["Merhaba", " dünya"]);
+        assert_eq!(completion.text, "Merhaba dünya");
+        assert_eq!(completion.usage, TokenUsage { prompt_tokens: 9, completion_tokens: 4, estimated: false });
+    }
+
+    #[tokio::test]
+    async fn parses_anthropic_stream() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(header("x-api-key", "test-key"))
+            .and(header("anthropic-version", ANTHROPIC_VERSION))
+            .respond_with(events(&[
+                r#"{"type":"message_start","message":{"usage":{"input_tokens":21,"output_tokens":1}}}"#,
+                r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Kısa"}}"#,
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":" özet"}}"#,
+                r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":5}}"#,
+                r#"{"type":"message_stop"}"#,
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"after stop"}}"#,
+            ]))
+            .mount(&server)
+            .await;
+
+        let (result, tokens) = complete(&client(ProviderKind::Anthropic, &server, 0), "Hi").await;
+        let completion = result.unwrap();
+        assert_eq!(tokens, vec!["Kısa", " özet"]);
+        assert_eq!(completion.text, "Kısa özet");
+        assert_eq!(completion.usage, TokenUsage { prompt_tokens: 21, completion_tokens: 5, estimated: false });
+    }
+
+    #[tokio::test]
+    async fn parses_gemini_stream() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/models/gemini-2.0-flash:streamGenerateContent"))
+            .and(header("x-goog-api-key", "test-key"))
+            .respond_with(events(&[
+                r#"{"candidates":[{"content":{"parts":[{"text":"Toplantı"}]}}],"usageMetadata":{"promptTokenCount":30,"candidatesTokenCount":2}}"#,
+                r#"{"candidates":[{"content":{"parts":[{"text":" bitti."}]}}],"usageMetadata":{"promptTokenCount":30,"candidatesTokenCount":6}}"#,
+            ]))
+            .mount(&server)
+            .await;
+
+        let (result, tokens) = complete(&client(ProviderKind::Gemini, &server, 0), "Hi").await;
+        let completion = result.unwrap();
+        assert_eq!(tokens, vec!["Toplantı", " bitti."]);
+        assert_eq!(completion.text, "Toplantı bitti.");
+        // Running totals, not added up
+        assert_eq!(completion.usage, TokenUsage { prompt_tokens: 30, completion_tokens: 6, estimated: false });
+    }
+
+    #[tokio::test]
+    async fn error_events_fail_the_request() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(events(&[
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Kıs"}}"#,
+                r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+            ]))
+            .mount(&server)
+            .await;
+
+        let (result, _) = complete(&client(ProviderKind::Anthropic, &server, 0), "Hi").await;
+        assert!(matches!(result, Err(NotlokError::AiRequestFailed { message, .. }) if message == "Overloaded"));
+    }
+
+    #[tokio::test]
+    async fn missing_counts_are_estimated() {
+        let server = MockServer::start().await;
+        // Input reported, output never is
+        Mock::given(method("POST"))
+            .respond_with(events(&[
+                r#"{"type":"message_start","message":{"usage":{"input_tokens":21}}}"#,
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Kısa özet"}}"#,
+                r#"{"type":"message_stop"}"#,
+            ]))
+            .mount(&server)
+            .await;
+        let (result, _) = complete(&client(ProviderKind::Anthropic, &server, 0), "Hi").await;
+        let usage = result.unwrap().usage;
+        assert_eq!(usage, TokenUsage { prompt_tokens: 21, completion_tokens: estimate_tokens("Kısa özet"), estimated: true });
+
+        // A local server answering without streaming or counts
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "choices": [{ "message": { "content": "Tamam" } }] })))
+            .mount(&server)
+            .await;
+        let prompt = "Bu toplantıyı özetle";
+        let (result, tokens) = complete(&client(ProviderKind::Local, &server, 0), prompt).await;
+        let completion = result.unwrap();
+        assert_eq!(tokens, vec!["Tamam"]);
+        assert_eq!(completion.usage, TokenUsage {
+            prompt_tokens: estimate_tokens(prompt),
+            completion_tokens: estimate_tokens("Tamam"),
+            estimated: true,
+        });
+    }
+
+    #[test]
+    fn usage_adds_up_and_stays_estimated() {
+        let mut total = TokenUsage { prompt_tokens: 10, completion_tokens: 2, estimated: false };
+        total.add(TokenUsage { prompt_tokens: 5, completion_tokens: 1, estimated: true });
+        total.add(TokenUsage { prompt_tokens: 1, completion_tokens: 1, estimated: false });
+        assert_eq!(total, TokenUsage { prompt_tokens: 16, completion_tokens: 4, estimated: true });
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcdefg"), 2);
+        assert_eq!(estimate_tokens("ğüşıöç"), 2);
+    }
+
+    #[test]
+    fn cloud_providers_need_a_key_and_model() {
+        let config = AiConfig::default();
+        assert!(matches!(provider(ProviderKind::Gemini, &config, None), Err(NotlokError::MissingApiKey { provider: ProviderKind::Gemini })));
+        assert!(provider(ProviderKind::Local, &config, None).is_ok());
+        let config = AiConfig { openai: Endpoint { base_url: config.openai.base_url.clone(), model: None }, ..AiConfig::default() };
+        assert!(matches!(provider(ProviderKind::OpenAi, &config, Some("k".to_string())), Err(NotlokError::InvalidConfig { .. })));
+    }
+}
@@ -1,5 +1,27 @@
+use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
 use serde::{Serialize, Deserialize};
+use tauri::{AppHandle, Emitter};
+use crate::error::NotlokError;
+use crate::session::{RecordingSession, SessionMetadata};
+use crate::vad::SAMPLE_RATE;
+
+mod devices;
+mod levels;
+mod lifecycle;
+mod mixer;
+pub(crate) mod ring;
+
+pub use devices::spawn_device_watcher;
+pub use levels::{AudioSource, AudioStats, SourceDiagnostics};
+pub use lifecycle::{CaptureError, RecorderState, StopSignal};
+pub use ring::CaptureSink;
+use levels::LevelMeters;
+use lifecycle::{CaptureThread, Lifecycle, Workers};
+use mixer::Mixer;
+use ring::SourceReader;
 
 #[cfg(target_os = "macos")]
 mod macos;
@@ -7,31 +29,369 @@ mod macos;
 #[cfg(target_os = "windows")]
 mod windows;
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+mod microphone;
+
+#[cfg(target_os = "macos")]
+use macos as platform;
+
+#[cfg(target_os = "windows")]
+use windows as platform;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use lifecycle::CaptureContext;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError};
+
+// Rate of audio-level events, also the resolution of the silence monitor
+const METER_INTERVAL: Duration = Duration::from_millis(100);
+
+// How often the consumer thread drains the capture rings
+const CONSUMER_INTERVAL: Duration = Duration::from_millis(10);
+
+// Audio kept while paused so the first syllable that triggers a resume isn't lost
+const PREROLL_SAMPLES: usize = 16000 / 2;
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct AudioDevice {
     pub id: String,
     pub name: String,
     pub is_default: bool,
 }
 
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct AutoPauseConfig {
+    pub enabled: bool,
+    pub pause_after_secs: u32, // 0 disables auto-pause
+    pub stop_after_mins: u32,  // 0 disables auto-stop
+    pub threshold: f32,        // average level below which the input counts as silent
+}
+
+impl Default for AutoPauseConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            pause_after_secs: 30,
+            stop_after_mins: 15,
+            threshold: crate::vad::SILENCE_THRESHOLD,
+        }
+    }
+}
+
+/// Input settings kept across restarts
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct InputConfig {
+    pub fallback_to_default: bool, // see AudioRecorder::set_fallback_to_default
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self { fallback_to_default: true }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct RecordingPauseEvent {
+    pub auto: bool,
+    pub silent_secs: f64,
+}
+
+#[derive(Default)]
+struct PauseGate {
+    paused: bool,
+    auto: bool, // paused by the silence monitor, so speech may resume it
+    preroll: VecDeque<f32>,
+}
+
+/// Pause state shared between the commands and the silence monitor
+#[derive(Clone)]
+struct PauseControl {
+    gate: Arc<Mutex<PauseGate>>,
+    buffer: Arc<Mutex<Vec<f32>>>,
+    session: Arc<Mutex<Option<RecordingSession>>>,
+}
+
+impl PauseControl {
+    fn is_paused(&self) -> bool {
+        self.gate.lock().unwrap().paused
+    }
+
+    // Stores mixed samples, or keeps the most recent ones as preroll while paused
+    fn append(&self, samples: &[f32]) {
+        if samples.is_empty() {
+            return;
+        }
+        let mut gate = self.gate.lock().unwrap();
+        if gate.paused {
+            gate.preroll.extend(samples.iter().copied());
+            let excess = gate.preroll.len().saturating_sub(PREROLL_SAMPLES);
+            gate.preroll.drain(..excess);
+        } else {
+            self.buffer.lock().unwrap().extend_from_slice(samples);
+        }
+    }
+
+    // Returns false if already paused
+    fn pause(&self, auto: bool) -> bool {
+        let mut gate = self.gate.lock().unwrap();
+        if gate.paused {
+            return false;
+        }
+        gate.paused = true;
+        gate.auto = auto;
+
+        let audio_offset = self.buffer.lock().unwrap().len() as f64 / SAMPLE_RATE as f64;
+        if let Some(session) = self.session.lock().unwrap().as_mut() {
+            session.pause(audio_offset, auto);
+        }
+        true
+    }
+
+    // Returns false if not paused
+    fn resume(&self) -> bool {
+        let mut gate = self.gate.lock().unwrap();
+        if !gate.paused {
+            return false;
+        }
+        let preroll: Vec<f32> = gate.preroll.drain(..).collect();
+        if gate.auto {
+            self.buffer.lock().unwrap().extend(preroll);
+        }
+        gate.paused = false;
+        gate.auto = false;
+
+        if let Some(session) = self.session.lock().unwrap().as_mut() {
+            session.resume();
+        }
+        true
+    }
+
+    fn finish(&self) {
+        self.gate.lock().unwrap().paused = false;
+        if let Some(session) = self.session.lock().unwrap().as_mut() {
+            session.finish();
+        }
+    }
+}
+
+/// Which input the microphone thread opens and how it reacts to that device disappearing
+#[cfg_attr(not(any(target_os = "macos", target_os = "windows")), allow(dead_code))]
+pub struct MicrophoneOptions {
+    pub device: Option<String>, // None follows the system default
+    pub fallback_to_default: bool,
+    pub session: Arc<Mutex<Option<RecordingSession>>>,
+}
+
+/// Where the platform capture paths deliver their 16kHz mono samples, one ring per source
+pub struct CaptureSinks {
+    pub microphone: CaptureSink,
+    pub system: CaptureSink,
+}
+
+// How long a source may take to open its stream, ScreenCaptureKit can be slow on first use
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+const START_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Each source captures on its own thread so a failure is attributed to that source
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn spawn_source<F>(
+    source: AudioSource,
+    stop: &Arc<StopSignal>,
+    app: &AppHandle,
+    capture: F,
+) -> (CaptureThread, Receiver<()>)
+where
+    F: FnOnce(&mut CaptureContext) -> Result<(), NotlokError> + Send + 'static,
+{
+    let (ready, started) = sync_channel(1);
+    let mut ctx = CaptureContext::new(source, stop.clone(), ready, app.clone());
+    let handle = std::thread::spawn(move || {
+        let result = capture(&mut ctx);
+        // Start failures are returned by start_recording, only later ones become events
+        if let Err(e) = &result {
+            if ctx.is_started() {
+                ctx.reporter().report(e.clone());
+            }
+        }
+        result
+    });
+    (CaptureThread { source, handle }, started)
+}
+
+/// Starts both sources and waits until each one is running. If either fails to start,
+/// the other is stopped again and the start errors are returned.
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn spawn_capture_threads(
+    sinks: CaptureSinks,
+    stop: &Arc<StopSignal>,
+    app: &AppHandle,
+    options: MicrophoneOptions,
+) -> Result<Vec<CaptureThread>, NotlokError> {
+    let CaptureSinks { microphone, system } = sinks;
+    let pending = [
+        spawn_source(AudioSource::System, stop, app, move |ctx| {
+            platform::capture_system_audio(system, ctx)
+        }),
+        spawn_source(AudioSource::Microphone, stop, app, move |ctx| {
+            microphone::capture_microphone(microphone, ctx, options)
+        }),
+    ];
+
+    let mut threads = Vec::new();
+    let mut errors = Vec::new();
+    for (thread, started) in pending {
+        let error = match started.recv_timeout(START_TIMEOUT) {
+            Ok(()) => {
+                threads.push(thread);
+                continue;
+            }
+            // The thread returned before starting, its result holds the reason
+            Err(RecvTimeoutError::Disconnected) => match thread.handle.join() {
+                Ok(Err(e)) => e,
+                Ok(Ok(())) => NotlokError::internal("Capture ended before it started"),
+                Err(_) => NotlokError::internal("Capture thread panicked"),
+            },
+            // Left detached, it exits on its own once the stop signal is set
+            Err(RecvTimeoutError::Timeout) => NotlokError::CaptureTimeout,
+        };
+        errors.push(CaptureError {
+            source: thread.source,
+            error,
+        });
+    }
+
+    if errors.is_empty() {
+        return Ok(threads);
+    }
+
+    stop.stop();
+    for thread in threads {
+        let _ = thread.handle.join();
+    }
+    Err(NotlokError::CaptureFailed { errors })
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn spawn_capture_threads(
+    _sinks: CaptureSinks,
+    _stop: &Arc<StopSignal>,
+    _app: &AppHandle,
+    _options: MicrophoneOptions,
+) -> Result<Vec<CaptureThread>, NotlokError> {
+    Err(NotlokError::PlatformNotSupported)
+}
+
 pub struct AudioRecorder {
-    is_recording: Arc<Mutex<bool>>,
     audio_buffer: Arc<Mutex<Vec<f32>>>,
     #[allow(dead_code)]
     sample_rate: u32, // Reserved for future use
     selected_input_device: Arc<Mutex<Option<String>>>,
+    fallback_to_default: Mutex<bool>,
+    gate: Arc<Mutex<PauseGate>>,
+    meters: Arc<LevelMeters>,
+    auto_pause: Arc<Mutex<AutoPauseConfig>>,
+    session: Arc<Mutex<Option<RecordingSession>>>,
+    lifecycle: Arc<Lifecycle>,
 }
 
 impl AudioRecorder {
     pub fn new() -> Self {
+        let audio_buffer = Arc::new(Mutex::new(Vec::new()));
+        let gate = Arc::new(Mutex::new(PauseGate::default()));
+        let session = Arc::new(Mutex::new(None));
+        let lifecycle = Arc::new(Lifecycle::new(PauseControl {
+            gate: gate.clone(),
+            buffer: audio_buffer.clone(),
+            session: session.clone(),
+        }));
+
         Self {
-            is_recording: Arc::new(Mutex::new(false)),
-            audio_buffer: Arc::new(Mutex::new(Vec::new())),
+            audio_buffer,
             sample_rate: 16000, // Whisper expects 16kHz
             selected_input_device: Arc::new(Mutex::new(None)),
+            fallback_to_default: Mutex::new(true),
+            gate,
+            meters: Arc::new(LevelMeters::default()),
+            auto_pause: Arc::new(Mutex::new(AutoPauseConfig::default())),
+            session,
+            lifecycle,
+        }
+    }
+
+    fn pause_control(&self) -> PauseControl {
+        PauseControl {
+            gate: self.gate.clone(),
+            buffer: self.audio_buffer.clone(),
+            session: self.session.clone(),
         }
     }
 
+    pub fn set_auto_pause_config(&self, config: AutoPauseConfig) {
+        *self.auto_pause.lock().unwrap() = config;
+    }
+
+    pub fn get_auto_pause_config(&self) -> AutoPauseConfig {
+        self.auto_pause.lock().unwrap().clone()
+    }
+
+    pub fn state(&self) -> RecorderState {
+        self.lifecycle.state()
+    }
+
+    pub fn pause_recording(&self) -> Result<(), NotlokError> {
+        if !self.lifecycle.is_recording() {
+            return Err(NotlokError::NotRecording);
+        }
+        if !self.pause_control().pause(false) {
+            return Err(NotlokError::AlreadyPaused);
+        }
+        Ok(())
+    }
+
+    pub fn resume_recording(&self) -> Result<(), NotlokError> {
+        if !self.lifecycle.is_recording() {
+            return Err(NotlokError::NotRecording);
+        }
+        if !self.pause_control().resume() {
+            return Err(NotlokError::NotPaused);
+        }
+        Ok(())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.gate.lock().unwrap().paused
+    }
+
+    /// Seconds recorded so far in the current session, paused time excluded
+    pub fn recording_duration(&self) -> f64 {
+        if !self.lifecycle.is_recording() {
+            return 0.0;
+        }
+        self.session.lock().unwrap()
+            .as_ref()
+            .map(|s| s.active_duration().as_secs_f64())
+            .unwrap_or(0.0)
+    }
+
+    pub fn session_metadata(&self) -> Option<SessionMetadata> {
+        self.session.lock().unwrap().as_ref().map(|s| s.metadata.clone())
+    }
+
+    /// Level stats over everything recorded this session, without touching the buffer
+    pub fn audio_stats(&self) -> AudioStats {
+        self.meters.total()
+    }
+
+    /// Per-source sample counts and levels of the last recording
+    pub fn capture_diagnostics(&self) -> Vec<SourceDiagnostics> {
+        self.meters.diagnostics()
+    }
+
+    pub fn buffer_len(&self) -> usize {
+        self.audio_buffer.lock().unwrap().len()
+    }
+
     pub fn get_audio_buffer(&self) -> Vec<f32> {
         self.audio_buffer.lock().unwrap().clone()
     }
@@ -44,109 +404,194 @@ impl AudioRecorder {
         self.selected_input_device.lock().unwrap().clone()
     }
 
-    pub fn list_input_devices() -> Result<Vec<AudioDevice>, String> {
-        use cpal::traits::{HostTrait, DeviceTrait};
-        
-        let host = cpal::default_host();
-        let default_device = host.default_input_device();
-        let default_name = default_device.as_ref()
-            .and_then(|d| d.name().ok());
-        
-        let mut devices = Vec::new();
-        
-        if let Ok(available_devices) = host.input_devices() {
-            for (index, device) in available_devices.enumerate() {
-                if let Ok(name) = device.name() {
-                    let is_default = Some(&name) == default_name.as_ref();
-                    devices.push(AudioDevice {
-                        id: format!("input_{}", index),
-                        name: name.clone(),
-                        is_default,
-                    });
-                }
-            }
-        }
-        
-        Ok(devices)
-    }
-
-    pub fn list_output_devices() -> Result<Vec<AudioDevice>, String> {
-        use cpal::traits::{HostTrait, DeviceTrait};
-        
-        let host = cpal::default_host();
-        let default_device = host.default_output_device();
-        let default_name = default_device.as_ref()
-            .and_then(|d| d.name().ok());
-        
-        let mut devices = Vec::new();
-        
-        if let Ok(available_devices) = host.output_devices() {
-            for (index, device) in available_devices.enumerate() {
-                if let Ok(name) = device.name() {
-                    let is_default = Some(&name) == default_name.as_ref();
-                    devices.push(AudioDevice {
-                        id: format!("output_{}", index),
-                        name: name.clone(),
-                        is_default,
-                    });
-                }
-            }
-        }
-        
-        Ok(devices)
+    /// Whether a recording moves to the default input when the selected device is unplugged
+    pub fn set_fallback_to_default(&self, enabled: bool) {
+        *self.fallback_to_default.lock().unwrap() = enabled;
+    }
+
+    pub fn get_fallback_to_default(&self) -> bool {
+        *self.fallback_to_default.lock().unwrap()
+    }
+
+    pub fn list_input_devices() -> Vec<AudioDevice> {
+        devices::input_devices()
+    }
+
+    pub fn list_output_devices() -> Vec<AudioDevice> {
+        devices::output_devices()
     }
 
-    pub fn start_recording(&self) -> Result<(), String> {
-        let mut is_recording = self.is_recording.lock().unwrap();
-        if *is_recording {
-            return Err("Already recording".to_string());
+    pub fn start_recording(&self, app: AppHandle) -> Result<(), NotlokError> {
+        let mut workers = self.lifecycle.workers.lock().unwrap();
+        if workers.is_some() {
+            return Err(NotlokError::AlreadyRecording);
         }
-        *is_recording = true;
-        drop(is_recording);
+        self.lifecycle.set_state(RecorderState::Starting);
 
         // Clear previous buffer
         self.audio_buffer.lock().unwrap().clear();
+        *self.gate.lock().unwrap() = PauseGate::default();
+        self.meters.reset();
+        *self.session.lock().unwrap() = Some(RecordingSession::new());
+
+        let (mic_sink, mic_reader) = ring::channel();
+        let (system_sink, system_reader) = ring::channel();
+        let sinks = CaptureSinks {
+            microphone: mic_sink,
+            system: system_sink,
+        };
 
         // Start platform-specific capture
-        #[cfg(target_os = "macos")]
-        {
-            let buffer = self.audio_buffer.clone();
-            let is_recording = self.is_recording.clone();
-            std::thread::spawn(move || {
-                if let Err(e) = macos::capture_audio(buffer, is_recording) {
-                    eprintln!("macOS audio capture error: {}", e);
+        let capture_stop = Arc::new(StopSignal::default());
+        let options = MicrophoneOptions {
+            device: self.get_input_device(),
+            fallback_to_default: self.get_fallback_to_default(),
+            session: self.session.clone(),
+        };
+        let capture = match spawn_capture_threads(sinks, &capture_stop, &app, options) {
+            Ok(capture) => capture,
+            Err(e) => {
+                self.lifecycle.set_state(RecorderState::Idle);
+                return Err(e);
+            }
+        };
+
+        let consumer_stop = Arc::new(StopSignal::default());
+        *workers = Some(Workers {
+            consumer: self.spawn_consumer(mic_reader, system_reader, consumer_stop.clone()),
+            monitor: self.spawn_level_monitor(app, capture_stop.clone()),
+            capture_stop,
+            consumer_stop,
+            capture,
+        });
+        self.lifecycle.set_state(RecorderState::Recording);
+
+        println!("Started recording system audio + microphone");
+        Ok(())
+    }
+
+    // Drains the per-source rings off the real-time threads: meters each source, mixes
+    // them and stores the result. Flushes whatever is left once told to stop, which only
+    // happens after the capture threads have been joined.
+    fn spawn_consumer(
+        &self,
+        mut microphone: SourceReader,
+        mut system: SourceReader,
+        stop: Arc<StopSignal>,
+    ) -> JoinHandle<()> {
+        let control = self.pause_control();
+        let meters = self.meters.clone();
+
+        std::thread::spawn(move || {
+            let mut mixer = Mixer::default();
+            let mut scratch = Vec::new();
+
+            loop {
+                let stopping = stop.is_stopped();
+
+                for (source, reader) in [
+                    (AudioSource::Microphone, &mut microphone),
+                    (AudioSource::System, &mut system),
+                ] {
+                    scratch.clear();
+                    if reader.read_into(&mut scratch) > 0 {
+                        // Levels are tracked while paused too, that's how speech resumes the recording
+                        meters.add(source, &scratch, !control.is_paused());
+                        mixer.push(source, &scratch);
+                    }
                 }
-            });
-        }
+                control.append(&mixer.mix(stopping));
 
-        #[cfg(target_os = "windows")]
-        {
-            let buffer = self.audio_buffer.clone();
-            let is_recording = self.is_recording.clone();
-            std::thread::spawn(move || {
-                if let Err(e) = windows::capture_audio(buffer, is_recording) {
-                    eprintln!("Windows audio capture error: {}", e);
+                if stopping {
+                    break;
                 }
-            });
-        }
+                stop.wait_timeout(CONSUMER_INTERVAL);
+            }
 
-        #[cfg(not(any(target_os = "macos", target_os = "windows")))]
-        {
-            return Err("Platform not supported".to_string());
-        }
+            if microphone.dropped() > 0 || system.dropped() > 0 {
+                eprintln!(
+                    "Capture rings overflowed, dropped {} microphone and {} system samples",
+                    microphone.dropped(),
+                    system.dropped()
+                );
+            }
+        })
+    }
 
-        println!("Started recording system audio + microphone");
-        Ok(())
+    // Emits audio-level at a fixed rate. Also pauses after prolonged silence, resumes
+    // on speech and stops the recording once the silence outlasts the configured limit
+    fn spawn_level_monitor(&self, app: AppHandle, stop: Arc<StopSignal>) -> JoinHandle<()> {
+        let lifecycle = self.lifecycle.clone();
+        let control = self.pause_control();
+        let meters = self.meters.clone();
+        let auto_pause = self.auto_pause.clone();
+
+        std::thread::spawn(move || {
+            let mut silent_for = Duration::ZERO;
+
+            while !stop.wait_timeout(METER_INTERVAL) {
+                let levels = meters.take_window();
+                let average = levels.microphone.average.max(levels.system.average);
+                let _ = app.emit("audio-level", levels);
+
+                let config = auto_pause.lock().unwrap().clone();
+                if !config.enabled {
+                    silent_for = Duration::ZERO;
+                    continue;
+                }
+
+                if average < config.threshold {
+                    silent_for += METER_INTERVAL;
+                } else {
+                    silent_for = Duration::ZERO;
+                }
+                let event = RecordingPauseEvent {
+                    auto: true,
+                    silent_secs: silent_for.as_secs_f64(),
+                };
+
+                if config.stop_after_mins > 0 && silent_for >= Duration::from_secs(config.stop_after_mins as u64 * 60) {
+                    // Stopping joins this thread, so it has to happen on another one
+                    std::thread::spawn(move || {
+                        match lifecycle.stop() {
+                            Ok(errors) => {
+                                for e in errors {
+                                    eprintln!("{}", e);
+                                }
+                            }
+                            Err(_) => return, // already stopped by the user
+                        }
+                        let _ = app.emit("recording-auto-stopped", event);
+                    });
+                    break;
+                }
+
+                // Manual pauses are left alone, only the user resumes those
+                let (paused, auto) = {
+                    let gate = control.gate.lock().unwrap();
+                    (gate.paused, gate.auto)
+                };
+                if paused && auto && silent_for.is_zero() {
+                    if control.resume() {
+                        let _ = app.emit("recording-resumed", event);
+                    }
+                } else if !paused
+                    && config.pause_after_secs > 0
+                    && silent_for >= Duration::from_secs(config.pause_after_secs as u64)
+                    && control.pause(true)
+                {
+                    let _ = app.emit("recording-paused", event);
+                }
+            }
+        })
     }
 
-    pub fn stop_recording(&self) -> Result<(), String> {
-        let mut is_recording = self.is_recording.lock().unwrap();
-        if !*is_recording {
-            return Err("Not recording".to_string());
-        }
-        *is_recording = false;
+    /// Stops capture and waits until every sample delivered so far is in the buffer.
+    /// Returns the errors the capture threads ended with.
+    pub fn stop_recording(&self) -> Result<Vec<CaptureError>, NotlokError> {
+        let errors = self.lifecycle.stop()?;
 
         println!("Stopped recording. Buffer size: {} samples", self.audio_buffer.lock().unwrap().len());
-        Ok(())
+        Ok(errors)
     }
 }
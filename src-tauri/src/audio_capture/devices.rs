@@ -0,0 +1,80 @@
+// Device enumeration and hot-plug detection. cpal has no change notifications, so the
+// device lists are polled and compared.
+
+use std::time::Duration;
+use cpal::traits::{DeviceTrait, HostTrait};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use super::AudioDevice;
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Serialize, PartialEq)]
+pub struct DevicesChangedEvent {
+    pub inputs: Vec<AudioDevice>,
+    pub outputs: Vec<AudioDevice>,
+}
+
+// Device names are the only identifier that survives a replug, indices shift
+fn describe<I>(devices: Result<I, cpal::DevicesError>, default: Option<cpal::Device>) -> Vec<AudioDevice>
+where
+    I: Iterator<Item = cpal::Device>,
+{
+    let default_name = default.and_then(|d| d.name().ok());
+    let Ok(devices) = devices else {
+        return Vec::new();
+    };
+    devices
+        .filter_map(|device| device.name().ok())
+        .map(|name| AudioDevice {
+            id: name.clone(),
+            is_default: Some(&name) == default_name.as_ref(),
+            name,
+        })
+        .collect()
+}
+
+pub fn input_devices() -> Vec<AudioDevice> {
+    let host = cpal::default_host();
+    describe(host.input_devices(), host.default_input_device())
+}
+
+pub fn output_devices() -> Vec<AudioDevice> {
+    let host = cpal::default_host();
+    describe(host.output_devices(), host.default_output_device())
+}
+
+/// Input device by id, the system default for None
+#[cfg_attr(not(any(target_os = "macos", target_os = "windows")), allow(dead_code))]
+pub fn find_input(id: Option<&str>) -> Option<cpal::Device> {
+    let host = cpal::default_host();
+    match id {
+        Some(id) => host
+            .input_devices()
+            .ok()?
+            .find(|device| device.name().map(|name| name == id).unwrap_or(false)),
+        None => host.default_input_device(),
+    }
+}
+
+/// Emits `devices-changed` whenever a device is added or removed, or the default changes
+pub fn spawn_device_watcher(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut last = DevicesChangedEvent {
+            inputs: input_devices(),
+            outputs: output_devices(),
+        };
+        loop {
+            std::thread::sleep(WATCH_INTERVAL);
+            let current = DevicesChangedEvent {
+                inputs: input_devices(),
+                outputs: output_devices(),
+            };
+            if current != last {
+                println!("Audio devices changed: {} inputs, {} outputs", current.inputs.len(), current.outputs.len());
+                let _ = app.emit("devices-changed", current.clone());
+                last = current;
+            }
+        }
+    });
+}
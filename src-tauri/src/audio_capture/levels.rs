@@ -0,0 +1,160 @@
+use std::sync::Mutex;
+use serde::Serialize;
+use crate::vad::{SAMPLE_RATE, SILENCE_THRESHOLD};
+
+#[derive(Clone, Copy, Serialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioSource {
+    Microphone,
+    System,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct AudioStats {
+    pub average: f32,
+    pub rms: f32,
+    pub peak: f32,
+    pub silence: f32,
+}
+
+/// What one source delivered during the last recording, for explaining an empty buffer
+#[derive(Clone, Serialize, Debug)]
+pub struct SourceDiagnostics {
+    pub source: AudioSource,
+    pub received_seconds: f64, // including audio dropped while paused
+    pub recorded_seconds: f64,
+    pub levels: AudioStats,    // over everything received
+}
+
+#[derive(Clone, Serialize)]
+pub struct AudioLevelEvent {
+    pub microphone: AudioStats,
+    pub system: AudioStats,
+}
+
+/// Running level analysis, updated incrementally as samples arrive
+#[derive(Clone, Default)]
+pub struct LevelAccumulator {
+    sum_abs: f64,
+    sum_sq: f64,
+    peak: f32,
+    silent: usize,
+    count: usize,
+}
+
+impl LevelAccumulator {
+    pub fn add(&mut self, samples: &[f32]) {
+        for s in samples {
+            let level = s.abs();
+            self.sum_abs += level as f64;
+            self.sum_sq += (level * level) as f64;
+            self.peak = self.peak.max(level);
+            if level < SILENCE_THRESHOLD {
+                self.silent += 1;
+            }
+        }
+        self.count += samples.len();
+    }
+
+    pub fn merge(&mut self, other: &LevelAccumulator) {
+        self.sum_abs += other.sum_abs;
+        self.sum_sq += other.sum_sq;
+        self.peak = self.peak.max(other.peak);
+        self.silent += other.silent;
+        self.count += other.count;
+    }
+
+    pub fn seconds(&self) -> f64 {
+        self.count as f64 / SAMPLE_RATE as f64
+    }
+
+    pub fn stats(&self) -> AudioStats {
+        if self.count == 0 {
+            return AudioStats {
+                average: 0.0,
+                rms: 0.0,
+                peak: 0.0,
+                silence: 100.0,
+            };
+        }
+        AudioStats {
+            average: (self.sum_abs / self.count as f64) as f32,
+            rms: (self.sum_sq / self.count as f64).sqrt() as f32,
+            peak: self.peak,
+            silence: (self.silent as f32 / self.count as f32) * 100.0,
+        }
+    }
+
+    /// Returns the stats since the last call and resets the accumulator
+    pub fn take(&mut self) -> AudioStats {
+        std::mem::take(self).stats()
+    }
+}
+
+#[derive(Default)]
+struct SourceLevels {
+    window: LevelAccumulator,   // drained at every meter tick
+    total: LevelAccumulator,    // everything kept in the buffer this session
+    received: LevelAccumulator, // everything the source delivered this session
+}
+
+/// Per-source meters shared by the capture callbacks and the meter thread
+#[derive(Default)]
+pub struct LevelMeters {
+    microphone: Mutex<SourceLevels>,
+    system: Mutex<SourceLevels>,
+}
+
+impl LevelMeters {
+    fn source(&self, source: AudioSource) -> &Mutex<SourceLevels> {
+        match source {
+            AudioSource::Microphone => &self.microphone,
+            AudioSource::System => &self.system,
+        }
+    }
+
+    /// `recorded` is false for samples dropped while paused, they only show up on the live meter
+    pub fn add(&self, source: AudioSource, samples: &[f32], recorded: bool) {
+        let mut levels = self.source(source).lock().unwrap();
+        levels.window.add(samples);
+        levels.received.add(samples);
+        if recorded {
+            levels.total.add(samples);
+        }
+    }
+
+    /// Levels since the previous tick, per source
+    pub fn take_window(&self) -> AudioLevelEvent {
+        AudioLevelEvent {
+            microphone: self.microphone.lock().unwrap().window.take(),
+            system: self.system.lock().unwrap().window.take(),
+        }
+    }
+
+    /// Levels over everything recorded this session, both sources combined
+    pub fn total(&self) -> AudioStats {
+        let mut total = self.microphone.lock().unwrap().total.clone();
+        total.merge(&self.system.lock().unwrap().total);
+        total.stats()
+    }
+
+    pub fn diagnostics(&self) -> Vec<SourceDiagnostics> {
+        [AudioSource::Microphone, AudioSource::System]
+            .into_iter()
+            .map(|source| {
+                let levels = self.source(source).lock().unwrap();
+                SourceDiagnostics {
+                    source,
+                    received_seconds: levels.received.seconds(),
+                    recorded_seconds: levels.total.seconds(),
+                    levels: levels.received.stats(),
+                }
+            })
+            .collect()
+    }
+
+    pub fn reset(&self) {
+        *self.microphone.lock().unwrap() = SourceLevels::default();
+        *self.system.lock().unwrap() = SourceLevels::default();
+    }
+}
@@ -0,0 +1,204 @@
+// Recorder lifecycle: Idle -> Starting -> Recording -> Stopping -> Idle
+//
+// Every thread a recording spawns is joined on stop, in order: capture threads first so
+// their last samples reach the rings, then the consumer so it drains and flushes them.
+
+use std::fmt;
+use std::sync::mpsc::SyncSender;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use super::{AudioSource, PauseControl};
+use crate::error::NotlokError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecorderState {
+    Idle,
+    Starting,
+    Recording,
+    Stopping,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CaptureError {
+    pub source: AudioSource,
+    pub error: NotlokError,
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let source = match self.source {
+            AudioSource::Microphone => "Microphone",
+            AudioSource::System => "System audio",
+        };
+        write!(f, "{} capture failed: {}", source, self.error)
+    }
+}
+
+/// Emits `capture-error` for failures after the source has started, e.g. an unplugged
+/// device or a stream error callback. Cheap to clone into the stream callbacks.
+#[cfg_attr(not(any(target_os = "macos", target_os = "windows")), allow(dead_code))] // Only built by the platform capture paths
+#[derive(Clone)]
+pub struct ErrorReporter {
+    source: AudioSource,
+    app: AppHandle,
+}
+
+#[cfg_attr(not(any(target_os = "macos", target_os = "windows")), allow(dead_code))]
+impl ErrorReporter {
+    pub fn report(&self, error: NotlokError) {
+        let error = CaptureError {
+            source: self.source,
+            error,
+        };
+        eprintln!("{}", error);
+        let _ = self.app.emit("capture-error", error);
+    }
+}
+
+/// Handed to each capture thread. `start_recording` blocks until every thread has
+/// either called `started` or returned its start error.
+#[cfg_attr(not(any(target_os = "macos", target_os = "windows")), allow(dead_code))]
+pub struct CaptureContext {
+    pub stop: Arc<StopSignal>,
+    ready: Option<SyncSender<()>>,
+    reporter: ErrorReporter,
+}
+
+#[cfg_attr(not(any(target_os = "macos", target_os = "windows")), allow(dead_code))]
+impl CaptureContext {
+    pub(super) fn new(
+        source: AudioSource,
+        stop: Arc<StopSignal>,
+        ready: SyncSender<()>,
+        app: AppHandle,
+    ) -> Self {
+        Self {
+            stop,
+            ready: Some(ready),
+            reporter: ErrorReporter { source, app },
+        }
+    }
+
+    /// Called once the stream is running, errors returned after this are runtime failures
+    pub fn started(&mut self) {
+        if let Some(ready) = self.ready.take() {
+            let _ = ready.send(());
+        }
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.ready.is_none()
+    }
+
+    pub fn reporter(&self) -> ErrorReporter {
+        self.reporter.clone()
+    }
+}
+
+/// Wakes the waiting threads as soon as stop is requested, instead of them polling a flag
+#[derive(Default)]
+pub struct StopSignal {
+    stopped: Mutex<bool>,
+    cv: Condvar,
+}
+
+impl StopSignal {
+    pub fn stop(&self) {
+        *self.stopped.lock().unwrap() = true;
+        self.cv.notify_all();
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        *self.stopped.lock().unwrap()
+    }
+
+    /// Waits up to `timeout`, returns true once stop has been requested
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let stopped = self.stopped.lock().unwrap();
+        let (stopped, _) = self.cv.wait_timeout_while(stopped, timeout, |s| !*s).unwrap();
+        *stopped
+    }
+
+    pub fn wait(&self) {
+        let stopped = self.stopped.lock().unwrap();
+        let _stopped = self.cv.wait_while(stopped, |s| !*s).unwrap();
+    }
+}
+
+pub struct CaptureThread {
+    pub source: AudioSource,
+    pub handle: JoinHandle<Result<(), NotlokError>>,
+}
+
+pub struct Workers {
+    pub capture_stop: Arc<StopSignal>,
+    pub consumer_stop: Arc<StopSignal>,
+    pub capture: Vec<CaptureThread>,
+    pub consumer: JoinHandle<()>,
+    pub monitor: JoinHandle<()>,
+}
+
+pub struct Lifecycle {
+    state: Mutex<RecorderState>,
+    pub(super) workers: Mutex<Option<Workers>>,
+    control: PauseControl,
+}
+
+impl Lifecycle {
+    pub(super) fn new(control: PauseControl) -> Self {
+        Self {
+            state: Mutex::new(RecorderState::Idle),
+            workers: Mutex::new(None),
+            control,
+        }
+    }
+
+    pub fn state(&self) -> RecorderState {
+        *self.state.lock().unwrap()
+    }
+
+    pub(super) fn set_state(&self, state: RecorderState) {
+        *self.state.lock().unwrap() = state;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.state() == RecorderState::Recording
+    }
+
+    /// Stops and joins every recording thread. Returns the errors the capture threads
+    /// ended with; the audio they delivered before failing is kept.
+    pub fn stop(&self) -> Result<Vec<CaptureError>, NotlokError> {
+        // Held for the whole shutdown, so a concurrent stop waits for this one to finish
+        let mut workers = self.workers.lock().unwrap();
+        let Some(workers) = workers.take() else {
+            return Err(NotlokError::NotRecording);
+        };
+        self.set_state(RecorderState::Stopping);
+
+        workers.capture_stop.stop();
+        let mut errors = Vec::new();
+        for thread in workers.capture {
+            let error = match thread.handle.join() {
+                Ok(Ok(())) => continue,
+                Ok(Err(e)) => e,
+                Err(_) => NotlokError::internal("Capture thread panicked"),
+            };
+            errors.push(CaptureError {
+                source: thread.source,
+                error,
+            });
+        }
+
+        workers.consumer_stop.stop();
+        let _ = workers.consumer.join();
+        let _ = workers.monitor.join();
+
+        self.control.finish();
+        self.set_state(RecorderState::Idle);
+        Ok(errors)
+    }
+}
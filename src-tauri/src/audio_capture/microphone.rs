@@ -0,0 +1,142 @@
+// Microphone capture shared by the platform backends. Follows the input device through
+// hot-plug: when it disappears the stream is reopened on the default input and the switch
+// is logged in the session, the recording itself keeps going.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use cpal::traits::{DeviceTrait, StreamTrait};
+use super::lifecycle::ErrorReporter;
+use super::{devices, CaptureContext, CaptureSink, MicrophoneOptions};
+use crate::error::NotlokError;
+
+// How often the open device is checked for removal
+const DEVICE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+struct ActiveStream {
+    name: String,
+    lost: Arc<AtomicBool>, // set by the error callback when the device goes away
+    _stream: cpal::Stream,
+}
+
+// The selected device, or the default input when it is gone and fallback is enabled
+fn pick_device(options: &MicrophoneOptions) -> Option<cpal::Device> {
+    match &options.device {
+        Some(id) => devices::find_input(Some(id))
+            .or_else(|| options.fallback_to_default.then(|| devices::find_input(None)).flatten()),
+        None => devices::find_input(None),
+    }
+}
+
+fn log_switch(options: &MicrophoneOptions, from: &str, to: &str) {
+    println!("Switched microphone from {} to {}", from, to);
+    if let Some(session) = options.session.lock().unwrap().as_mut() {
+        session.switch_device(from, to);
+    }
+}
+
+fn open_stream(
+    device: cpal::Device,
+    sink: &Arc<Mutex<CaptureSink>>,
+    reporter: ErrorReporter,
+) -> Result<ActiveStream, NotlokError> {
+    let name = device.name().unwrap_or_default();
+
+    let supported_config = device.default_input_config()
+        .map_err(NotlokError::audio_stream)?;
+
+    let sample_rate = supported_config.sample_rate().0;
+    let channels = supported_config.channels();
+
+    let config = cpal::StreamConfig {
+        channels,
+        sample_rate: cpal::SampleRate(sample_rate),
+        buffer_size: cpal::BufferSize::Default,
+    };
+
+    let lost = Arc::new(AtomicBool::new(false));
+    let lost_flag = lost.clone();
+    let dropped = sink.lock()?.drop_counter();
+    let sink = sink.clone();
+
+    let stream = device.build_input_stream(
+        &config,
+        move |data: &[f32], _: &cpal::InputCallbackInfo| {
+            // Never blocks, the lock is only contended while a replacement stream takes over.
+            // What can't be pushed then is counted like ring overflow.
+            match sink.try_lock() {
+                Ok(mut sink) => sink.push_interleaved(data, channels, sample_rate),
+                Err(_) => dropped.add_interleaved(data, channels, sample_rate),
+            }
+        },
+        move |err| match err {
+            cpal::StreamError::DeviceNotAvailable => lost_flag.store(true, Ordering::Relaxed),
+            err => reporter.report(NotlokError::audio_stream(err)),
+        },
+        None,
+    ).map_err(|e| match e {
+        cpal::BuildStreamError::DeviceNotAvailable => NotlokError::DeviceUnavailable { device: Some(name.clone()) },
+        e => NotlokError::audio_stream(e),
+    })?;
+
+    stream.play().map_err(NotlokError::audio_stream)?;
+
+    println!("Using microphone: {}", name);
+    Ok(ActiveStream {
+        name,
+        lost,
+        _stream: stream,
+    })
+}
+
+pub fn capture_microphone(
+    sink: CaptureSink,
+    ctx: &mut CaptureContext,
+    options: MicrophoneOptions,
+) -> Result<(), NotlokError> {
+    // Outlives the individual streams, each one pushes into the same ring
+    let sink = Arc::new(Mutex::new(sink));
+
+    let device = pick_device(&options).ok_or_else(|| NotlokError::DeviceUnavailable {
+        device: options.device.clone(),
+    })?;
+    let active = open_stream(device, &sink, ctx.reporter())?;
+    let mut name = active.name.clone();
+    let mut stream = Some(active);
+    if let Some(id) = options.device.as_deref().filter(|id| *id != name) {
+        log_switch(&options, id, &name);
+    }
+
+    ctx.started();
+
+    // Wait until recording stops, reopening the input whenever its device disappears
+    while !ctx.stop.wait_timeout(DEVICE_CHECK_INTERVAL) {
+        let healthy = stream.as_ref().is_some_and(|s| !s.lost.load(Ordering::Relaxed))
+            && devices::find_input(Some(&name)).is_some();
+        if healthy {
+            continue;
+        }
+
+        // Dropping the dead stream releases the sink for its replacement
+        let just_lost = stream.take().is_some();
+        let reopened = pick_device(&options)
+            .ok_or_else(|| NotlokError::DeviceUnavailable { device: Some(name.clone()) })
+            .and_then(|device| open_stream(device, &sink, ctx.reporter()));
+
+        match reopened {
+            Ok(active) => {
+                if active.name != name {
+                    log_switch(&options, &name, &active.name);
+                    name = active.name.clone();
+                }
+                stream = Some(active);
+            }
+            // Keeps retrying every check, but only reports once per disconnect
+            Err(e) if just_lost => ctx.reporter().report(e),
+            Err(_) => {}
+        }
+    }
+
+    println!("Microphone capture stopped");
+    Ok(())
+}
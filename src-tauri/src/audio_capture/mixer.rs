@@ -0,0 +1,47 @@
+// Mixes the microphone and system streams into the single mono track that gets transcribed
+
+use std::collections::VecDeque;
+use super::AudioSource;
+use crate::vad::SAMPLE_RATE;
+
+// How far one source may run ahead before the other is treated as silent. Covers a
+// source that delivers in larger blocks as well as one that delivers nothing at all.
+const MAX_SKEW: usize = SAMPLE_RATE / 2;
+
+#[derive(Default)]
+pub struct Mixer {
+    microphone: VecDeque<f32>,
+    system: VecDeque<f32>,
+}
+
+impl Mixer {
+    pub fn push(&mut self, source: AudioSource, samples: &[f32]) {
+        match source {
+            AudioSource::Microphone => self.microphone.extend(samples.iter().copied()),
+            AudioSource::System => self.system.extend(samples.iter().copied()),
+        }
+    }
+
+    /// Mixes whatever both sources have delivered. With `flush` everything pending is
+    /// mixed, padding the shorter source with silence.
+    pub fn mix(&mut self, flush: bool) -> Vec<f32> {
+        let mic = self.microphone.len();
+        let system = self.system.len();
+        let longest = mic.max(system);
+        let n = if flush {
+            longest
+        } else if mic.abs_diff(system) > MAX_SKEW {
+            longest - MAX_SKEW
+        } else {
+            mic.min(system)
+        };
+
+        let mut out = Vec::with_capacity(n);
+        for _ in 0..n {
+            let a = self.microphone.pop_front().unwrap_or(0.0);
+            let b = self.system.pop_front().unwrap_or(0.0);
+            out.push((a + b).clamp(-1.0, 1.0));
+        }
+        out
+    }
+}
@@ -0,0 +1,166 @@
+// Lock-free SPSC ring buffers between the real-time capture callbacks and the consumer thread.
+// The callback side never blocks or allocates: frames are downmixed and resampled straight
+// into the ring, overflow is counted and dropped.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use rtrb::{Consumer, Producer, RingBuffer};
+use crate::vad::SAMPLE_RATE;
+
+// Two seconds of 16kHz mono per source, the consumer drains every few milliseconds
+const RING_CAPACITY: usize = SAMPLE_RATE * 2;
+
+pub fn channel() -> (CaptureSink, SourceReader) {
+    let (producer, consumer) = RingBuffer::new(RING_CAPACITY);
+    let dropped = Arc::new(AtomicUsize::new(0));
+    (
+        CaptureSink { producer, dropped: dropped.clone() },
+        SourceReader { consumer, dropped },
+    )
+}
+
+/// Producer end, owned by a single capture callback
+pub struct CaptureSink {
+    producer: Producer<f32>,
+    dropped: Arc<AtomicUsize>,
+}
+
+impl CaptureSink {
+    /// Pushes samples that are already 16kHz mono
+    pub fn push(&mut self, samples: &[f32]) {
+        self.write(samples.iter().copied(), samples.len());
+    }
+
+    /// Downmixes interleaved frames to mono and resamples them to 16kHz with linear
+    /// interpolation, computed while writing into the ring
+    pub fn push_interleaved(&mut self, data: &[f32], channels: u16, sample_rate: u32) {
+        let channels = usize::from(channels.max(1));
+        let frames = data.len() / channels;
+        let mono = |i: usize| data[i * channels..(i + 1) * channels].iter().sum::<f32>() / channels as f32;
+        let ratio = sample_rate as f64 / SAMPLE_RATE as f64;
+        let len = converted_len(frames, sample_rate);
+        let samples = (0..len).map(|i| {
+            let position = i as f64 * ratio;
+            let index = position as usize;
+            let frac = (position - index as f64) as f32;
+            if index + 1 < frames {
+                mono(index) * (1.0 - frac) + mono(index + 1) * frac
+            } else {
+                mono(index)
+            }
+        });
+        self.write(samples, len);
+    }
+
+    /// Counter for samples that never reach this sink, shared with its reader
+    pub fn drop_counter(&self) -> DropCounter {
+        DropCounter(self.dropped.clone())
+    }
+
+    fn write(&mut self, samples: impl Iterator<Item = f32>, len: usize) {
+        let n = len.min(self.producer.slots());
+        let written = match self.producer.write_chunk_uninit(n) {
+            Ok(chunk) => chunk.fill_from_iter(samples),
+            Err(_) => 0,
+        };
+        if written < len {
+            self.dropped.fetch_add(len - written, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Counts what a callback had to discard without reaching the sink, in the same units as
+/// the ring's own overflow
+#[derive(Clone)]
+pub struct DropCounter(Arc<AtomicUsize>);
+
+impl DropCounter {
+    pub fn add_interleaved(&self, data: &[f32], channels: u16, sample_rate: u32) {
+        let frames = data.len() / usize::from(channels.max(1));
+        self.0.fetch_add(converted_len(frames, sample_rate), Ordering::Relaxed);
+    }
+}
+
+/// How many 16kHz samples `frames` frames at `sample_rate` become
+fn converted_len(frames: usize, sample_rate: u32) -> usize {
+    (frames as f64 / (sample_rate as f64 / SAMPLE_RATE as f64)) as usize
+}
+
+/// Consumer end, owned by the consumer thread
+pub struct SourceReader {
+    consumer: Consumer<f32>,
+    dropped: Arc<AtomicUsize>,
+}
+
+impl SourceReader {
+    /// Moves everything currently in the ring into `out`, returns how many samples were read
+    pub fn read_into(&mut self, out: &mut Vec<f32>) -> usize {
+        let n = self.consumer.slots();
+        match self.consumer.read_chunk(n) {
+            Ok(chunk) => {
+                let (first, second) = chunk.as_slices();
+                out.extend_from_slice(first);
+                out.extend_from_slice(second);
+                chunk.commit_all();
+                n
+            }
+            Err(_) => 0,
+        }
+    }
+
+    /// Samples the callback had to drop because the ring was full
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain(reader: &mut SourceReader) -> Vec<f32> {
+        let mut out = Vec::new();
+        reader.read_into(&mut out);
+        out
+    }
+
+    #[test]
+    fn stereo_is_averaged() {
+        let (mut sink, mut reader) = channel();
+        sink.push_interleaved(&[1.0, 0.0, 0.5, 0.5, -1.0, 0.0], 2, 16000);
+        assert_eq!(drain(&mut reader), vec![0.5, 0.5, -0.5]);
+    }
+
+    #[test]
+    fn resamples_to_16k() {
+        let (mut sink, mut reader) = channel();
+        // 10ms at 48kHz
+        let ramp: Vec<f32> = (0..480).map(|i| i as f32).collect();
+        sink.push_interleaved(&ramp, 1, 48000);
+        let out = drain(&mut reader);
+        assert_eq!(out.len(), 160);
+        assert_eq!(&out[..3], &[0.0, 3.0, 6.0]);
+
+        // 44.1kHz falls between source samples
+        sink.push_interleaved(&ramp[..441], 1, 44100);
+        let out = drain(&mut reader);
+        assert_eq!(out.len(), 160);
+        assert!((out[1] - 2.75625).abs() < 1e-4);
+    }
+
+    #[test]
+    fn discarded_frames_are_counted_as_output_samples() {
+        let (sink, reader) = channel();
+        sink.drop_counter().add_interleaved(&[0.0; 960], 2, 48000);
+        assert_eq!(reader.dropped(), 160);
+    }
+
+    #[test]
+    fn overflow_is_counted() {
+        let (mut sink, mut reader) = channel();
+        sink.push(&vec![0.0; RING_CAPACITY - 10]);
+        sink.push_interleaved(&[0.0; 60], 2, 16000);
+        assert_eq!(reader.dropped(), 20);
+        assert_eq!(drain(&mut reader).len(), RING_CAPACITY);
+    }
+}
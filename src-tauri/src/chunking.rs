@@ -0,0 +1,236 @@
+// Long-audio chunking
+// Speech regions longer than a Whisper window are decoded in chunks: each chunk owns the
+// audio between two cuts and also decodes a little past both of them, so a word on a cut
+// is heard whole by at least one side. Cuts are moved to the quietest nearby frame.
+
+use crate::transcription::TranscriptSegment;
+use crate::vad::{rms, SpeechRegion, SAMPLE_RATE};
+
+// Whisper decodes 30s at a time, longer inputs only add memory and repetition loops
+const CHUNK_SAMPLES: usize = SAMPLE_RATE * 30;
+// Decoded on both sides of a cut
+const OVERLAP_SAMPLES: usize = SAMPLE_RATE * 2;
+// How far back from the longest possible chunk a quieter cut is searched
+const CUT_SEARCH_SAMPLES: usize = SAMPLE_RATE * 4;
+const CUT_FRAME_SAMPLES: usize = SAMPLE_RATE / 50; // 20 ms
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Chunk {
+    pub decode: SpeechRegion, // audio sent to the model
+    pub keep: SpeechRegion,   // segments centred outside this belong to a neighbour
+}
+
+/// Splits the speech regions into chunks no longer than a Whisper window
+pub fn plan_chunks(audio: &[f32], regions: &[SpeechRegion]) -> Vec<Chunk> {
+    let max_keep = CHUNK_SAMPLES - 2 * OVERLAP_SAMPLES;
+    let mut chunks = Vec::new();
+
+    for region in regions {
+        let mut start = region.start;
+        while region.end - start > max_keep {
+            let cut = quietest_frame(audio, start + max_keep - CUT_SEARCH_SAMPLES, start + max_keep);
+            chunks.push(chunk(region, start, cut));
+            start = cut;
+        }
+        chunks.push(chunk(region, start, region.end));
+    }
+    chunks
+}
+
+fn chunk(region: &SpeechRegion, start: usize, end: usize) -> Chunk {
+    Chunk {
+        decode: SpeechRegion {
+            start: start.saturating_sub(OVERLAP_SAMPLES).max(region.start),
+            end: (end + OVERLAP_SAMPLES).min(region.end),
+        },
+        keep: SpeechRegion { start, end },
+    }
+}
+
+fn quietest_frame(audio: &[f32], from: usize, to: usize) -> usize {
+    (from..to)
+        .step_by(CUT_FRAME_SAMPLES)
+        .min_by(|&a, &b| {
+            let level = |i: usize| rms(&audio[i..(i + CUT_FRAME_SAMPLES).min(audio.len())]);
+            level(a).total_cmp(&level(b))
+        })
+        .unwrap_or(to)
+}
+
+/// Adds a chunk's segments (already in recording time) to the merged list, dropping the
+/// ones centred outside its keep range and repeats of text decoded by the previous chunk
+pub fn merge(merged: &mut Vec<TranscriptSegment>, chunk: &Chunk, segments: Vec<TranscriptSegment>) {
+    let keep_start = chunk.keep.start as f64 / SAMPLE_RATE as f64;
+    let keep_end = chunk.keep.end as f64 / SAMPLE_RATE as f64;
+
+    for segment in segments {
+        let middle = (segment.start + segment.end) / 2.0;
+        if middle < keep_start || middle >= keep_end {
+            continue;
+        }
+        // A segment straddling the cut can land on both sides with slightly different timing
+        let repeated = merged.last().is_some_and(|last| {
+            last.end > segment.start && same_text(&last.text, &segment.text)
+        });
+        if !repeated {
+            merged.push(segment);
+        }
+    }
+}
+
+/// Lowercase words without punctuation, for comparing decoded text
+pub fn normalized(text: &str) -> String {
+    text.chars()
+        .flat_map(char::to_lowercase)
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn same_text(a: &str, b: &str) -> bool {
+    let (a, b) = (normalized(a), normalized(b));
+    !a.is_empty() && !b.is_empty() && (a.contains(&b) || b.contains(&a))
+}
+
+/// The end of the text so far, given to the next chunk as context
+pub fn prompt_context(merged: &[TranscriptSegment], max_chars: usize) -> String {
+    let mut context = String::new();
+    for segment in merged.iter().rev() {
+        if context.chars().count() >= max_chars {
+            break;
+        }
+        context = format!("{} {}", segment.text.trim(), context);
+    }
+
+    let chars: Vec<char> = context.trim().chars().collect();
+    if chars.len() <= max_chars {
+        return chars.into_iter().collect();
+    }
+    // Starts on a word boundary
+    let tail: String = chars[chars.len() - max_chars..].iter().collect();
+    match tail.split_once(' ') {
+        Some((_, rest)) => rest.to_string(),
+        None => tail,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: usize = SAMPLE_RATE;
+
+    fn region(start_secs: usize, end_secs: usize) -> SpeechRegion {
+        SpeechRegion { start: start_secs * SECOND, end: end_secs * SECOND }
+    }
+
+    fn segment(start: f64, end: f64, text: &str) -> TranscriptSegment {
+        TranscriptSegment { start, end, text: text.to_string(), speaker: None, words: Vec::new(), avg_logprob: None }
+    }
+
+    // Keep ranges cover the region without gaps, decode ranges fit a window and stay inside it
+    fn assert_tiles(chunks: &[Chunk], region: SpeechRegion) {
+        assert_eq!(chunks.first().unwrap().keep.start, region.start);
+        assert_eq!(chunks.last().unwrap().keep.end, region.end);
+        for pair in chunks.windows(2) {
+            assert_eq!(pair[0].keep.end, pair[1].keep.start);
+        }
+        for chunk in chunks {
+            assert!(chunk.decode.sample_count() <= CHUNK_SAMPLES);
+            assert!(chunk.decode.start >= region.start && chunk.decode.end <= region.end);
+            assert!(chunk.decode.start <= chunk.keep.start && chunk.decode.end >= chunk.keep.end);
+        }
+    }
+
+    #[test]
+    fn short_region_is_one_chunk() {
+        let audio = vec![0.0; 40 * SECOND];
+        let regions = [region(3, 23), region(30, 40)];
+        let chunks = plan_chunks(&audio, &regions);
+        assert_eq!(chunks, vec![
+            Chunk { decode: regions[0], keep: regions[0] },
+            Chunk { decode: regions[1], keep: regions[1] },
+        ]);
+        assert!(plan_chunks(&audio, &[]).is_empty());
+    }
+
+    #[test]
+    fn longest_single_chunk() {
+        let max_keep = CHUNK_SAMPLES - 2 * OVERLAP_SAMPLES;
+        let audio = vec![0.0; CHUNK_SAMPLES];
+        let fits = SpeechRegion { start: 0, end: max_keep };
+        assert_eq!(plan_chunks(&audio, &[fits]).len(), 1);
+        let too_long = SpeechRegion { start: 0, end: max_keep + 1 };
+        let chunks = plan_chunks(&audio, &[too_long]);
+        assert_eq!(chunks.len(), 2);
+        assert_tiles(&chunks, too_long);
+    }
+
+    #[test]
+    fn exact_multiple_of_the_chunk_length() {
+        // In silence every cut is equally quiet, the first frame searched wins
+        let audio = vec![0.0; 60 * SECOND];
+        let chunks = plan_chunks(&audio, &[region(0, 60)]);
+        let keeps: Vec<SpeechRegion> = chunks.iter().map(|c| c.keep).collect();
+        assert_eq!(keeps, vec![region(0, 22), region(22, 44), region(44, 60)]);
+        assert_eq!(chunks[0].decode, region(0, 24));
+        assert_eq!(chunks[1].decode, region(20, 46));
+        assert_eq!(chunks[2].decode, region(42, 60));
+        assert_tiles(&chunks, region(0, 60));
+    }
+
+    #[test]
+    fn cuts_at_the_quietest_frame() {
+        let mut audio: Vec<f32> = (0..50 * SECOND).map(|i| 0.5 * (i as f32 * 0.1).sin()).collect();
+        let pause = 24 * SECOND + SECOND / 2;
+        audio[pause..pause + CUT_FRAME_SAMPLES].fill(0.0);
+        let chunks = plan_chunks(&audio, &[region(0, 50)]);
+        assert_eq!(chunks[0].keep.end, pause);
+        assert_tiles(&chunks, region(0, 50));
+    }
+
+    #[test]
+    fn segment_straddling_the_cut_is_kept_once() {
+        let first = Chunk { decode: region(0, 26), keep: region(0, 24) };
+        let second = Chunk { decode: region(22, 50), keep: region(24, 50) };
+
+        // Centred on the same side of the cut by both chunks
+        let mut merged = Vec::new();
+        merge(&mut merged, &first, vec![segment(20.0, 23.0, " Başlayalım."), segment(23.0, 25.4, " Bütçe hazır mı?")]);
+        merge(&mut merged, &second, vec![segment(23.1, 25.3, " Bütçe hazır mı?"), segment(25.5, 28.0, " Evet.")]);
+        let texts: Vec<&str> = merged.iter().map(|s| s.text.trim()).collect();
+        assert_eq!(texts, vec!["Başlayalım.", "Bütçe hazır mı?", "Evet."]);
+
+        // Centred on different sides, the repeat is recognised by its text
+        let mut merged = Vec::new();
+        merge(&mut merged, &first, vec![segment(22.5, 24.9, " Bütçe hazır mı?")]);
+        merge(&mut merged, &second, vec![segment(23.3, 25.0, " bütçe hazır mı"), segment(25.5, 28.0, " Evet.")]);
+        let texts: Vec<&str> = merged.iter().map(|s| s.text.trim()).collect();
+        assert_eq!(texts, vec!["Bütçe hazır mı?", "Evet."]);
+
+        // Same words later on are not a repeat
+        merge(&mut merged, &second, vec![segment(30.0, 32.0, " Evet.")]);
+        assert_eq!(merged.len(), 3);
+    }
+
+    #[test]
+    fn normalizes_text() {
+        assert_eq!(normalized("  BÜTÇE, hazır   mı? "), "bütçe hazır mı");
+        assert!(!same_text("...", "..."));
+    }
+
+    #[test]
+    fn prompt_context_cuts_on_characters_and_words() {
+        let merged = vec![
+            segment(0.0, 2.0, " Toplantıya başlıyoruz."),
+            segment(2.0, 6.0, " Gündemde üç madde var: bütçe, işe alım ve ofis taşınması."),
+        ];
+        assert_eq!(prompt_context(&merged, 200), "Toplantıya başlıyoruz. Gündemde üç madde var: bütçe, işe alım ve ofis taşınması.");
+        assert_eq!(prompt_context(&merged, 30), "işe alım ve ofis taşınması.");
+        assert_eq!(prompt_context(&merged, 20), "ve ofis taşınması.");
+        assert_eq!(prompt_context(&[segment(0.0, 2.0, "Çekoslovakyalılaştıramadıklarımızdan")], 10), "larımızdan");
+        assert_eq!(prompt_context(&[], 20), "");
+    }
+}
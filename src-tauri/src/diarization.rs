@@ -0,0 +1,334 @@
+// Speaker diarization
+// Runs after transcription: each timestamped segment gets a speaker embedding from an
+// on-device ONNX model, and the embeddings are clustered so segments spoken by the same
+// person share a speaker id. Fully offline, CPU only.
+//
+// The model is expected to take 80-bin Kaldi-style fbank features shaped [1, frames, 80]
+// and return one embedding, like the WeSpeaker and 3D-Speaker ONNX exports.
+
+use std::f32::consts::PI;
+use serde::{Deserialize, Serialize};
+use crate::error::NotlokError;
+use crate::transcription::TranscriptSegment;
+use crate::vad::SAMPLE_RATE;
+
+// Segments shorter than this give unreliable embeddings, they take a neighbour's speaker
+const MIN_SEGMENT_SAMPLES: usize = SAMPLE_RATE / 2;
+
+const FRAME_LEN: usize = SAMPLE_RATE / 40;    // 25 ms
+const FRAME_SHIFT: usize = SAMPLE_RATE / 100; // 10 ms
+const FFT_SIZE: usize = 512;
+const NUM_MEL_BINS: usize = 80;
+const PREEMPHASIS: f32 = 0.97;
+// The models are trained on 16-bit PCM levels
+const PCM_SCALE: f32 = 32768.0;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiarizationConfig {
+    pub enabled: bool,
+    pub model_path: String,
+    pub threshold: f32,              // cosine similarity needed to merge two speakers
+    pub max_speakers: Option<usize>, // keeps merging past the threshold until at most this many
+}
+
+impl Default for DiarizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model_path: String::new(),
+            threshold: 0.5,
+            max_speakers: None,
+        }
+    }
+}
+
+#[cfg(feature = "onnx")]
+struct SpeakerEmbedder {
+    session: ort::session::Session,
+    fbank: Fbank,
+}
+
+#[cfg(feature = "onnx")]
+impl SpeakerEmbedder {
+    fn new(model_path: &str) -> Result<Self, NotlokError> {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let session = ort::session::Session::builder()
+            .and_then(|builder| builder.with_intra_threads(threads))
+            .and_then(|builder| builder.commit_from_file(model_path))
+            .map_err(|e| NotlokError::ModelLoadFailed { message: e.to_string() })?;
+        Ok(Self {
+            session,
+            fbank: Fbank::new(),
+        })
+    }
+
+    fn embed(&mut self, samples: &[f32]) -> Result<Vec<f32>, NotlokError> {
+        let (frames, features) = self.fbank.compute(samples);
+        let run = |session: &mut ort::session::Session| -> ort::Result<Vec<f32>> {
+            let input = ort::value::Tensor::from_array(([1, frames, NUM_MEL_BINS], features))?;
+            let outputs = session.run(ort::inputs![input])?;
+            let (_, embedding) = outputs[0].try_extract_tensor::<f32>()?;
+            Ok(embedding.to_vec())
+        };
+        run(&mut self.session).map_err(|e| NotlokError::TranscriptionFailed {
+            message: format!("Speaker embedding failed: {}", e),
+        })
+    }
+}
+
+#[cfg(not(feature = "onnx"))]
+struct SpeakerEmbedder;
+
+#[cfg(not(feature = "onnx"))]
+impl SpeakerEmbedder {
+    fn new(_model_path: &str) -> Result<Self, NotlokError> {
+        Err(NotlokError::ModelLoadFailed {
+            message: "Speaker diarization requires ONNX Runtime, which is not enabled in this build".to_string(),
+        })
+    }
+
+    fn embed(&mut self, _samples: &[f32]) -> Result<Vec<f32>, NotlokError> {
+        Ok(Vec::new())
+    }
+}
+
+/// Assigns a speaker id to every segment, numbered from 0 in order of first appearance.
+/// `audio` is the full 16kHz buffer the segment timestamps refer to.
+pub fn diarize(audio: &[f32], segments: &mut [TranscriptSegment], config: &DiarizationConfig) -> Result<(), NotlokError> {
+    if !config.enabled || segments.is_empty() {
+        return Ok(());
+    }
+
+    let mut embedder = SpeakerEmbedder::new(&config.model_path)?;
+
+    let mut embedded = Vec::new(); // indices into `segments`
+    let mut embeddings = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let start = ((segment.start * SAMPLE_RATE as f64) as usize).min(audio.len());
+        let end = ((segment.end * SAMPLE_RATE as f64) as usize).min(audio.len());
+        if end.saturating_sub(start) < MIN_SEGMENT_SAMPLES {
+            continue;
+        }
+        embedded.push(i);
+        embeddings.push(normalize(embedder.embed(&audio[start..end])?));
+    }
+
+    let labels = cluster(&embeddings, config.threshold, config.max_speakers);
+    for (&i, &label) in embedded.iter().zip(&labels) {
+        segments[i].speaker = Some(label);
+    }
+
+    // Short segments belong to whoever spoke just before (or after, at the very start)
+    let mut previous = segments.iter().find_map(|s| s.speaker);
+    for segment in segments.iter_mut() {
+        match segment.speaker {
+            Some(speaker) => previous = Some(speaker),
+            None => segment.speaker = previous,
+        }
+    }
+
+    Ok(())
+}
+
+/// Gives each segment the speaker of the labelled segment it overlaps most. Used for the
+/// translation, which is decoded separately and doesn't split segments the same way.
+pub fn copy_speakers(from: &[TranscriptSegment], to: &mut [TranscriptSegment]) {
+    for segment in to.iter_mut() {
+        segment.speaker = from.iter()
+            .filter_map(|s| Some((s.end.min(segment.end) - s.start.max(segment.start), s.speaker?)))
+            .filter(|(overlap, _)| *overlap > 0.0)
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, speaker)| speaker);
+    }
+}
+
+fn normalize(mut v: Vec<f32>) -> Vec<f32> {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+    v
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Average-linkage agglomerative clustering over cosine similarity of unit-length
+/// embeddings. Returns a cluster per embedding, numbered in order of first appearance.
+fn cluster(embeddings: &[Vec<f32>], threshold: f32, max_speakers: Option<usize>) -> Vec<usize> {
+    let n = embeddings.len();
+    let mut similarity: Vec<Vec<f32>> = embeddings.iter()
+        .map(|a| embeddings.iter().map(|b| dot(a, b)).collect())
+        .collect();
+    let mut members: Vec<Vec<usize>> = (0..n).map(|i| vec![i]).collect();
+    let mut active = vec![true; n];
+    let mut count = n;
+
+    while count > 1 {
+        let mut best: Option<(usize, usize, f32)> = None;
+        for a in (0..n).filter(|&a| active[a]) {
+            for b in (a + 1..n).filter(|&b| active[b]) {
+                if best.is_none_or(|(_, _, s)| similarity[a][b] > s) {
+                    best = Some((a, b, similarity[a][b]));
+                }
+            }
+        }
+        let Some((a, b, best_similarity)) = best else { break };
+        let over_limit = max_speakers.is_some_and(|max| count > max.max(1));
+        if best_similarity < threshold && !over_limit {
+            break;
+        }
+
+        // Merge b into a, the new row is the size-weighted average of both
+        let (size_a, size_b) = (members[a].len() as f32, members[b].len() as f32);
+        for k in 0..n {
+            if active[k] && k != a && k != b {
+                let merged = (size_a * similarity[a][k] + size_b * similarity[b][k]) / (size_a + size_b);
+                similarity[a][k] = merged;
+                similarity[k][a] = merged;
+            }
+        }
+        let moved = std::mem::take(&mut members[b]);
+        members[a].extend(moved);
+        active[b] = false;
+        count -= 1;
+    }
+
+    // Clusters are merged into the lower index, so the first member is always the smallest
+    let mut labels = vec![0; n];
+    let mut clusters: Vec<&Vec<usize>> = members.iter().filter(|m| !m.is_empty()).collect();
+    clusters.sort_by_key(|m| m[0]);
+    for (label, cluster) in clusters.into_iter().enumerate() {
+        for &i in cluster {
+            labels[i] = label;
+        }
+    }
+    labels
+}
+
+/// Log mel filterbank features, matching Kaldi's `compute-fbank-feats` defaults closely
+/// enough for the embedding models (povey window, no dither), with mean normalization.
+#[cfg_attr(not(feature = "onnx"), allow(dead_code))]
+struct Fbank {
+    window: Vec<f32>,
+    filters: Vec<Vec<f32>>, // NUM_MEL_BINS rows of FFT_SIZE / 2 + 1 weights
+}
+
+fn mel(hz: f32) -> f32 {
+    1127.0 * (1.0 + hz / 700.0).ln()
+}
+
+#[cfg_attr(not(feature = "onnx"), allow(dead_code))]
+impl Fbank {
+    fn new() -> Self {
+        let window = (0..FRAME_LEN)
+            .map(|i| (0.5 - 0.5 * (2.0 * PI * i as f32 / (FRAME_LEN - 1) as f32).cos()).powf(0.85))
+            .collect();
+
+        let low = mel(20.0);
+        let high = mel(SAMPLE_RATE as f32 / 2.0);
+        let step = (high - low) / (NUM_MEL_BINS + 1) as f32;
+        let filters = (0..NUM_MEL_BINS)
+            .map(|m| {
+                let left = low + m as f32 * step;
+                let center = left + step;
+                let right = center + step;
+                (0..=FFT_SIZE / 2)
+                    .map(|bin| {
+                        let m = mel(bin as f32 * SAMPLE_RATE as f32 / FFT_SIZE as f32);
+                        if m <= left || m >= right {
+                            0.0
+                        } else if m <= center {
+                            (m - left) / (center - left)
+                        } else {
+                            (right - m) / (right - center)
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self { window, filters }
+    }
+
+    /// Returns the frame count and the row-major [frames, NUM_MEL_BINS] features
+    fn compute(&self, samples: &[f32]) -> (usize, Vec<f32>) {
+        if samples.len() < FRAME_LEN {
+            return (0, Vec::new());
+        }
+        let frames = 1 + (samples.len() - FRAME_LEN) / FRAME_SHIFT;
+        let mut features = Vec::with_capacity(frames * NUM_MEL_BINS);
+        let mut re = vec![0.0; FFT_SIZE];
+        let mut im = vec![0.0; FFT_SIZE];
+
+        for f in 0..frames {
+            let frame = &samples[f * FRAME_SHIFT..f * FRAME_SHIFT + FRAME_LEN];
+            let mean = frame.iter().sum::<f32>() / FRAME_LEN as f32;
+            re.fill(0.0);
+            im.fill(0.0);
+            for i in 0..FRAME_LEN {
+                let current = (frame[i] - mean) * PCM_SCALE;
+                let previous = if i > 0 { (frame[i - 1] - mean) * PCM_SCALE } else { current };
+                re[i] = (current - PREEMPHASIS * previous) * self.window[i];
+            }
+            fft(&mut re, &mut im);
+
+            for filter in &self.filters {
+                let energy: f32 = filter.iter().enumerate()
+                    .map(|(bin, w)| w * (re[bin] * re[bin] + im[bin] * im[bin]))
+                    .sum();
+                features.push(energy.max(f32::EPSILON).ln());
+            }
+        }
+
+        for bin in 0..NUM_MEL_BINS {
+            let mean = (0..frames).map(|f| features[f * NUM_MEL_BINS + bin]).sum::<f32>() / frames as f32;
+            for f in 0..frames {
+                features[f * NUM_MEL_BINS + bin] -= mean;
+            }
+        }
+
+        (frames, features)
+    }
+}
+
+// In-place iterative radix-2 FFT, the length must be a power of two
+#[cfg_attr(not(feature = "onnx"), allow(dead_code))]
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let a = start + k;
+                let b = a + len / 2;
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
@@ -0,0 +1,134 @@
+// Errors returned by the commands. Serialized as `{ code, params }` with a stable code per
+// variant so the frontend can branch on the kind and show a localized message. Display
+// is English and only meant for logs.
+
+use crate::ai::ProviderKind;
+use crate::audio_capture::{CaptureError, SourceDiagnostics};
+use serde::Serialize;
+use std::sync::PoisonError;
+use thiserror::Error;
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    ScreenRecording,
+    Microphone,
+}
+
+#[derive(Clone, Debug, Serialize, Error)]
+#[serde(tag = "code", content = "params")]
+pub enum NotlokError {
+    #[error("Model not found: {model_id}")]
+    ModelNotFound { model_id: String },
+    #[error("Model not downloaded: {model_id}")]
+    ModelNotDownloaded { model_id: String },
+    #[error("Unsupported model type: {model_id}")]
+    UnsupportedModel { model_id: String },
+    #[error("Failed to load model: {message}")]
+    ModelLoadFailed { message: String },
+    #[error("No model loaded")]
+    NoModelLoaded,
+    #[error("Download of {model_id} failed: {message}")]
+    DownloadFailed { model_id: String, message: String },
+    #[error("Transcription failed: {message}")]
+    TranscriptionFailed { message: String },
+    #[error("Transcription cancelled")]
+    Cancelled,
+    #[error("No running transcription with id {job_id}")]
+    JobNotFound { job_id: u64 },
+    #[error("Too many transcriptions are waiting")]
+    Busy,
+    #[error("Language model server not reachable at {url}")]
+    LlmUnavailable { url: String },
+    #[error("{provider:?} request failed: {message}")]
+    AiRequestFailed {
+        provider: ProviderKind,
+        message: String,
+    },
+    #[error("{provider:?} rate limit reached")]
+    RateLimited { provider: ProviderKind },
+    #[error("No API key for {provider:?}")]
+    MissingApiKey { provider: ProviderKind },
+    #[error("Session not found: {session_id}")]
+    SessionNotFound { session_id: String },
+    #[error("Invalid meeting report: {message}")]
+    InvalidReport { message: String },
+
+    #[error("Already recording")]
+    AlreadyRecording,
+    #[error("Not recording")]
+    NotRecording,
+    #[error("Already paused")]
+    AlreadyPaused,
+    #[error("Not paused")]
+    NotPaused,
+    #[error("{permission:?} permission denied")]
+    PermissionDenied { permission: Permission },
+    #[error("Audio device unavailable: {}", .device.as_deref().unwrap_or("default"))]
+    DeviceUnavailable { device: Option<String> }, // None is the system default
+    #[error("Audio stream error: {message}")]
+    AudioStream { message: String },
+    #[error("Timed out waiting for the audio stream to start")]
+    CaptureTimeout,
+    #[error("{}", describe_capture_errors(.errors))]
+    CaptureFailed { errors: Vec<CaptureError> },
+    #[error("No audio was captured")]
+    NoAudioCaptured { sources: Vec<SourceDiagnostics> },
+    #[error("Platform not supported")]
+    PlatformNotSupported,
+
+    #[error("Invalid language: {language}")]
+    InvalidLanguage { language: String },
+    #[error("Invalid setting: {field}")]
+    InvalidConfig { field: String },
+    #[error("Secret storage error: {message}")]
+    SecretStore { message: String },
+    #[error("Invalid license key")]
+    LicenseInvalid,
+    #[error("License belongs to another email address")]
+    LicenseEmailMismatch,
+    #[error("License activation limit reached")]
+    ActivationLimitReached,
+    #[error("License server error: {message}")]
+    LicenseServer { message: String },
+    #[error("Notlok AI request failed ({status}): {message}")]
+    NotlokAiFailed { status: u16, message: String },
+    #[error("File error: {message}")]
+    Io { message: String },
+    #[error("Internal error: {message}")]
+    Internal { message: String },
+}
+
+fn describe_capture_errors(errors: &[CaptureError]) -> String {
+    let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+    messages.join("; ")
+}
+
+impl NotlokError {
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::Internal {
+            message: message.into(),
+        }
+    }
+
+    pub fn audio_stream(error: impl std::fmt::Display) -> Self {
+        Self::AudioStream {
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<std::io::Error> for NotlokError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io {
+            message: e.to_string(),
+        }
+    }
+}
+
+// A poisoned lock means another command panicked mid-update
+impl<T> From<PoisonError<T>> for NotlokError {
+    fn from(e: PoisonError<T>) -> Self {
+        Self::internal(e.to_string())
+    }
+}
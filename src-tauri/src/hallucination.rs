@@ -0,0 +1,202 @@
+// Hallucination filter
+// Whisper fills silence and music with text it has seen a lot in training: stock subtitle
+// credits, "thanks for watching" and the same phrase over and over. Each decoded chunk is
+// cleaned before it is merged, so a hallucination can't leak into the next chunk's prompt.
+// Dropping segments by no-speech probability isn't possible, whisper-rs 0.13 doesn't expose
+// it; a floor on the mean token log probability (`min_avg_logprob`) does that job instead.
+
+use serde::{Deserialize, Serialize};
+use crate::chunking::normalized;
+use crate::transcription::{TranscriptSegment, TranscriptWord};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HallucinationFilter {
+    pub enabled: bool,
+    // Segments decoded with a lower mean token log probability are dropped. Stands in for
+    // a no-speech probability threshold, which whisper-rs doesn't expose.
+    pub min_avg_logprob: f32,
+    pub max_repeats: usize, // a word or phrase repeated more often than this is a loop
+    pub max_ngram: usize,   // longest phrase, in words, checked for loops
+    pub blacklist: Vec<String>, // segments saying only one of these are dropped
+}
+
+impl Default for HallucinationFilter {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_avg_logprob: -1.2,
+            max_repeats: 3,
+            max_ngram: 8,
+            blacklist: [
+                "Altyazı M.K.",
+                "İzlediğiniz için teşekkürler",
+                "İzlediğiniz için teşekkür ederim",
+                "Abone olmayı unutmayın",
+                "Thank you for watching",
+                "Thanks for watching",
+                "Subtitles by the Amara.org community",
+                "Please subscribe",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+}
+
+impl HallucinationFilter {
+    pub fn apply(&self, segments: Vec<TranscriptSegment>) -> Vec<TranscriptSegment> {
+        if !self.enabled {
+            return segments;
+        }
+        let blacklist: Vec<String> = self.blacklist.iter().map(|p| normalized(p)).filter(|p| !p.is_empty()).collect();
+
+        let segments = segments.into_iter()
+            .filter(|s| s.avg_logprob.is_none_or(|logprob| logprob >= self.min_avg_logprob))
+            .map(|s| self.collapse_segment(s))
+            .filter(|s| {
+                let text = normalized(&s.text);
+                !text.is_empty() && !blacklist.contains(&text)
+            })
+            .collect();
+        // The same sentence as a run of segments is a loop too
+        collapse_loops(segments, 1, self.max_repeats, |s| normalized(&s.text))
+    }
+
+    // Word timestamps are kept in step with the text when there are any
+    fn collapse_segment(&self, mut segment: TranscriptSegment) -> TranscriptSegment {
+        if segment.words.is_empty() {
+            let words: Vec<&str> = segment.text.split_whitespace().collect();
+            let count = words.len();
+            let words = collapse_loops(words, self.max_ngram, self.max_repeats, |w| normalized(w));
+            if words.len() < count {
+                segment.text = format!(" {}", words.join(" "));
+            }
+        } else {
+            let count = segment.words.len();
+            segment.words = collapse_loops(segment.words, self.max_ngram, self.max_repeats, |w: &TranscriptWord| normalized(&w.text));
+            if segment.words.len() < count {
+                let words: Vec<&str> = segment.words.iter().map(|w| w.text.as_str()).collect();
+                segment.text = format!(" {}", words.join(" "));
+            }
+        }
+        segment
+    }
+}
+
+/// Replaces every run of more than `max_repeats` back-to-back copies of the same phrase
+/// (up to `max_ngram` items long) with a single copy. Items compare by `key`.
+fn collapse_loops<T>(items: Vec<T>, max_ngram: usize, max_repeats: usize, key: impl Fn(&T) -> String) -> Vec<T> {
+    let keys: Vec<String> = items.iter().map(&key).collect();
+    let mut keep = vec![true; items.len()];
+
+    let mut i = 0;
+    while i < keys.len() {
+        // Shortest phrase first, so "evet evet evet evet" is one word looping, not two
+        let repeat = (1..=max_ngram.min((keys.len() - i) / 2)).find_map(|n| {
+            let phrase = &keys[i..i + n];
+            if phrase.iter().all(String::is_empty) {
+                return None;
+            }
+            let copies = 1 + keys[i + n..].chunks_exact(n).take_while(|next| *next == phrase).count();
+            (copies > max_repeats).then_some((n, copies))
+        });
+        match repeat {
+            Some((n, copies)) => {
+                keep[i + n..i + n * copies].iter_mut().for_each(|k| *k = false);
+                i += n * copies;
+            }
+            None => i += 1,
+        }
+    }
+
+    items.into_iter().zip(keep).filter_map(|(item, keep)| keep.then_some(item)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Segments as Whisper returned them, in the shape sessions are saved in
+    fn fixture(name: &str) -> Vec<TranscriptSegment> {
+        let path = format!("{}/tests/fixtures/hallucination/{}.json", env!("CARGO_MANIFEST_DIR"), name);
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    fn texts(segments: &[TranscriptSegment]) -> Vec<&str> {
+        segments.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    #[test]
+    fn drops_low_logprob_and_blacklisted_segments() {
+        let segments = HallucinationFilter::default().apply(fixture("meeting_tail"));
+        assert_eq!(texts(&segments), vec![
+            " Tamam, o zaman bütçe konusuna geçelim.",
+            " Geçen çeyrekte pazarlama tarafında yüzde on beş aşım var.",
+            " Bir sonraki toplantıda tekrar bakarız.",
+            " Evet.", // no log probability, kept
+        ]);
+    }
+
+    #[test]
+    fn logprob_floor_is_configurable() {
+        let filter = HallucinationFilter { min_avg_logprob: -2.0, blacklist: Vec::new(), ..HallucinationFilter::default() };
+        assert_eq!(filter.apply(fixture("meeting_tail")).len(), 7);
+        let filter = HallucinationFilter { min_avg_logprob: -0.3, blacklist: Vec::new(), ..HallucinationFilter::default() };
+        assert_eq!(texts(&filter.apply(fixture("meeting_tail"))), vec![
+            " Tamam, o zaman bütçe konusuna geçelim.",
+            " Bir sonraki toplantıda tekrar bakarız.",
+            " Evet.",
+        ]);
+    }
+
+    #[test]
+    fn blacklist_matches_whole_segments_ignoring_case_and_punctuation() {
+        let filter = HallucinationFilter { blacklist: vec!["  bir SONRAKİ toplantıda tekrar bakarız! ".to_string()], ..HallucinationFilter::default() };
+        let segments = filter.apply(fixture("meeting_tail"));
+        assert!(!texts(&segments).contains(&" Bir sonraki toplantıda tekrar bakarız."));
+        // Stock phrases from the default list are no longer removed
+        assert!(texts(&segments).contains(&" Thanks for watching!"));
+        // Only whole segments, not phrases inside one
+        let filter = HallucinationFilter { blacklist: vec!["bütçe".to_string()], ..HallucinationFilter::default() };
+        assert!(texts(&filter.apply(fixture("meeting_tail"))).contains(&" Tamam, o zaman bütçe konusuna geçelim."));
+    }
+
+    #[test]
+    fn collapses_word_loops_with_their_timestamps() {
+        let segments = HallucinationFilter::default().apply(fixture("word_loop"));
+        let words: Vec<(&str, f64, f64)> = segments[0].words.iter().map(|w| (w.text.as_str(), w.start, w.end)).collect();
+        assert_eq!(words, vec![("Evet", 12.0, 12.4), ("tamam.", 16.9, 17.6)]);
+        assert_eq!(segments[0].text, " Evet tamam.");
+        assert_eq!((segments[0].start, segments[0].end), (12.0, 17.6));
+    }
+
+    #[test]
+    fn collapses_phrase_loops_without_word_timestamps() {
+        let segments = HallucinationFilter::default().apply(fixture("word_loop"));
+        assert_eq!(segments[1].text, " Bunu yarın konuşalım");
+    }
+
+    #[test]
+    fn collapses_loops_across_segments() {
+        let segments = HallucinationFilter::default().apply(fixture("segment_loop"));
+        assert_eq!(texts(&segments), vec![" Sunumu herkese gönderdim.", " Teşekkürler.", " Bir dahaki haftaya görüşürüz."]);
+        // The copy that stays is the first one
+        assert_eq!((segments[1].start, segments[1].end), (3.1, 5.0));
+    }
+
+    #[test]
+    fn repeats_up_to_the_limit_are_kept() {
+        let filter = HallucinationFilter { max_repeats: 5, ..HallucinationFilter::default() };
+        assert_eq!(filter.apply(fixture("segment_loop")).len(), 7);
+        assert_eq!(filter.apply(fixture("word_loop"))[0].words.len(), 2); // six copies
+    }
+
+    #[test]
+    fn disabled_filter_changes_nothing() {
+        let filter = HallucinationFilter { enabled: false, ..HallucinationFilter::default() };
+        for name in ["meeting_tail", "word_loop", "segment_loop"] {
+            assert_eq!(texts(&filter.apply(fixture(name))), texts(&fixture(name)));
+        }
+    }
+}
@@ -0,0 +1,230 @@
+// Saved sessions
+// Every finished transcription is kept as one JSON file in the app data directory, named
+// after the recording session, so reports can be generated and stored against it later.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::ai::{ProviderKind, TokenUsage};
+use crate::error::NotlokError;
+use crate::report::{ActionItem, MeetingReport};
+use crate::session::SessionMetadata;
+use crate::summarize::ReportTemplate;
+use crate::transcription::Transcript;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub id: String,
+    pub created_at: u64, // unix ms
+    pub model: Option<String>,
+    pub language: String,
+    pub metadata: Option<SessionMetadata>, // None when the audio didn't come from a recording
+    pub transcript: Transcript,
+    #[serde(default)]
+    pub speakers: HashMap<usize, String>, // names given to diarized speakers, by index
+    #[serde(default)]
+    pub report: Option<SavedReport>,
+    #[serde(default)]
+    pub meeting_report: Option<SavedMeetingReport>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedReport {
+    pub text: String,
+    pub template: ReportTemplate,
+    pub provider: ProviderKind,
+    pub usage: TokenUsage,
+    pub created_at: u64, // unix ms
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedMeetingReport {
+    #[serde(flatten)]
+    pub report: MeetingReport,
+    pub provider: ProviderKind,
+    pub usage: TokenUsage,
+    pub created_at: u64, // unix ms
+}
+
+/// An action item with the meeting it came from
+#[derive(Clone, Debug, Serialize)]
+pub struct SessionActionItem {
+    pub session_id: String,
+    pub meeting_started_at: u64, // unix ms
+    #[serde(flatten)]
+    pub item: ActionItem,
+}
+
+pub struct History {
+    dir: PathBuf,
+}
+
+impl History {
+    pub fn new(dir: &Path) -> Self {
+        Self { dir: dir.join("sessions") }
+    }
+
+    // Ids come from the frontend, they must not reach outside the directory
+    fn path(&self, id: &str) -> Result<PathBuf, NotlokError> {
+        let valid = !id.is_empty() && id.len() <= 64 && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(NotlokError::SessionNotFound { session_id: id.to_string() });
+        }
+        Ok(self.dir.join(format!("{}.json", id)))
+    }
+
+    pub fn load(&self, id: &str) -> Result<SessionRecord, NotlokError> {
+        let data = match std::fs::read_to_string(self.path(id)?) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(NotlokError::SessionNotFound { session_id: id.to_string() });
+            }
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_str(&data).map_err(|e| NotlokError::internal(format!("Session {} is corrupt: {}", id, e)))
+    }
+
+    /// Replaces the saved session with the same id
+    pub fn save(&self, record: &SessionRecord) -> Result<(), NotlokError> {
+        let path = self.path(&record.id)?;
+        std::fs::create_dir_all(&self.dir)?;
+        let data = serde_json::to_string(record).map_err(|e| NotlokError::internal(e.to_string()))?;
+        // Written next to the target and renamed, like the settings
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, data)?;
+        std::fs::rename(temp_path, path)?;
+        Ok(())
+    }
+
+    /// Every saved session, newest first. Unreadable files are logged and skipped.
+    pub fn list(&self) -> Result<Vec<SessionRecord>, NotlokError> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut records = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let Some(id) = path.file_name().and_then(|n| n.to_str()).and_then(|n| n.strip_suffix(".json")) else {
+                continue;
+            };
+            match self.load(id) {
+                Ok(record) => records.push(record),
+                Err(e) => eprintln!("Skipping session {}: {}", id, e),
+            }
+        }
+        records.sort_by_key(|r| std::cmp::Reverse(r.created_at));
+        Ok(records)
+    }
+
+    /// Action items of every meeting with a report, by due date with undated ones last,
+    /// then newest meeting first
+    pub fn action_items(&self) -> Result<Vec<SessionActionItem>, NotlokError> {
+        let mut items: Vec<SessionActionItem> = self.list()?
+            .into_iter()
+            .flat_map(|record| {
+                let meeting_started_at = record.metadata.as_ref().map_or(record.created_at, |m| m.started_at);
+                let report = record.meeting_report.map(|r| r.report.action_items).unwrap_or_default();
+                let session_id = record.id;
+                report.into_iter().map(move |item| SessionActionItem {
+                    session_id: session_id.clone(),
+                    meeting_started_at,
+                    item,
+                })
+            })
+            .collect();
+        // Stable, so the newest-first order of the list holds within a due date
+        items.sort_by(|a, b| match (&a.item.due_date, &b.item.due_date) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        });
+        Ok(items)
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), NotlokError> {
+        match std::fs::remove_file(self.path(id)?) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(task: &str, due_date: Option<&str>) -> ActionItem {
+        ActionItem { task: task.to_string(), owner: None, due_date: due_date.map(String::from) }
+    }
+
+    fn record(id: &str, created_at: u64, action_items: Vec<ActionItem>) -> SessionRecord {
+        SessionRecord {
+            id: id.to_string(),
+            created_at,
+            model: None,
+            language: "tr".to_string(),
+            metadata: None,
+            transcript: Transcript::default(),
+            speakers: HashMap::new(),
+            report: None,
+            meeting_report: Some(SavedMeetingReport {
+                report: MeetingReport {
+                    summary: "s".to_string(),
+                    decisions: Vec::new(),
+                    action_items,
+                    open_questions: Vec::new(),
+                    topics: Vec::new(),
+                },
+                provider: ProviderKind::Local,
+                usage: TokenUsage::default(),
+                created_at,
+            }),
+        }
+    }
+
+    #[test]
+    fn action_items_dated_first_then_newest_meeting_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::new(dir.path());
+        history.save(&record("old", 1_000, vec![item("old undated", None), item("old late", Some("2024-03-01")), item("old early", Some("2024-02-01"))])).unwrap();
+        history.save(&record("new", 2_000, vec![item("new late", Some("2024-03-01")), item("new undated", None)])).unwrap();
+        history.save(&record("newest", 3_000, vec![item("newest undated 1", None), item("newest undated 2", None)])).unwrap();
+        let mut without_report = record("plain", 4_000, Vec::new());
+        without_report.meeting_report = None;
+        history.save(&without_report).unwrap();
+
+        let items = history.action_items().unwrap();
+        let tasks: Vec<(&str, &str)> = items.iter().map(|i| (i.session_id.as_str(), i.item.task.as_str())).collect();
+        assert_eq!(tasks, vec![
+            ("old", "old early"),
+            ("new", "new late"),
+            ("old", "old late"),
+            ("newest", "newest undated 1"),
+            ("newest", "newest undated 2"),
+            ("new", "new undated"),
+            ("old", "old undated"),
+        ]);
+        assert_eq!(items[0].meeting_started_at, 1_000);
+    }
+
+    #[test]
+    fn records_without_speaker_names_still_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::new(dir.path());
+        let mut saved = record("s1", 1_000, Vec::new());
+        saved.speakers.insert(1, "Ayşe".to_string());
+        history.save(&saved).unwrap();
+        assert_eq!(history.load("s1").unwrap().speakers.get(&1).map(String::as_str), Some("Ayşe"));
+
+        // Saved before speakers were named
+        let mut json: serde_json::Value = serde_json::to_value(&saved).unwrap();
+        json.as_object_mut().unwrap().remove("speakers");
+        std::fs::write(dir.path().join("sessions").join("s2.json"), json.to_string()).unwrap();
+        assert!(history.load("s2").unwrap().speakers.is_empty());
+        assert!(matches!(history.load("../s1"), Err(NotlokError::SessionNotFound { .. })));
+    }
+}
@@ -0,0 +1,49 @@
+// User-visible strings that have to be produced in Rust. Everything that can be is
+// translated by the frontend instead (errors come as codes, the catalog as enums); this
+// covers the remaining messages, in the language the UI reported with set_ui_language.
+
+use serde::{Deserialize, Serialize};
+use crate::summarize::ReportTemplate;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    Tr,
+    En,
+}
+
+pub enum Message<'a> {
+    ModelAlreadyDownloaded,
+    ModelDownloaded,
+    ModelLoaded { model_id: &'a str },
+    ReportInstructions(ReportTemplate), // Custom falls back to the summary
+    MeetingReportInstructions,
+}
+
+impl Message<'_> {
+    pub fn localize(&self, locale: Locale) -> String {
+        match (self, locale) {
+            (Message::ModelAlreadyDownloaded, Locale::Tr) => "Model zaten indirilmiş".to_string(),
+            (Message::ModelAlreadyDownloaded, Locale::En) => "Model is already downloaded".to_string(),
+            (Message::ModelDownloaded, Locale::Tr) => "Model başarıyla indirildi".to_string(),
+            (Message::ModelDownloaded, Locale::En) => "Model downloaded successfully".to_string(),
+            (Message::ModelLoaded { model_id }, Locale::Tr) => format!("Model yüklendi: {}", model_id),
+            (Message::ModelLoaded { model_id }, Locale::En) => format!("Model loaded: {}", model_id),
+            (Message::ReportInstructions(template), Locale::Tr) => match template {
+                ReportTemplate::MeetingNotes => "Bu toplantı transkriptinden detaylı toplantı notları oluştur. Katılımcıları, tartışılan konuları, önemli noktaları ve sonuçları madde madde listele. Türkçe yaz.",
+                ReportTemplate::MeetingSummary | ReportTemplate::Custom => "Bu toplantı transkriptini analiz et ve kısa bir yönetici özeti hazırla. Ana konuları, alınan kararları ve sonraki adımları 2-3 paragrafta özetle. Türkçe yaz.",
+                ReportTemplate::ActionItems => "Bu toplantı transkriptinden tüm aksiyon maddelerini çıkar. Her madde için sorumlu kişiyi (varsa), görevi ve son tarihi (varsa) belirt. Öncelik sırasına göre listele. Türkçe yaz.",
+                ReportTemplate::DecisionLog => "Bu toplantı transkriptinden alınan tüm kararları tespit et. Her karar için bağlamı, kararın kendisini, gerekçesini ve etkisini açıkla. Türkçe yaz.",
+            }.to_string(),
+            (Message::ReportInstructions(template), Locale::En) => match template {
+                ReportTemplate::MeetingNotes => "Create detailed meeting notes from this transcript. List participants, topics discussed, key points, and outcomes in bullet points. Write in English.",
+                ReportTemplate::MeetingSummary | ReportTemplate::Custom => "Analyze this meeting transcript and prepare a brief executive summary. Summarize main topics, decisions made, and next steps in 2-3 paragraphs. Write in English.",
+                ReportTemplate::ActionItems => "Extract all action items from this meeting transcript. For each item, specify the responsible person (if mentioned), the task, and deadline (if mentioned). List by priority. Write in English.",
+                ReportTemplate::DecisionLog => "Identify all decisions made in this meeting transcript. For each decision, explain the context, the decision itself, rationale, and impact. Write in English.",
+            }.to_string(),
+            (Message::MeetingReportInstructions, Locale::Tr) => "Bu toplantı transkriptinden yapılandırılmış bir toplantı raporu çıkar. Metinleri Türkçe yaz, JSON anahtarlarını olduğu gibi bırak.".to_string(),
+            (Message::MeetingReportInstructions, Locale::En) => "Extract a structured meeting report from this transcript. Write the texts in English.".to_string(),
+        }
+    }
+}
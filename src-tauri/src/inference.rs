@@ -0,0 +1,162 @@
+// Inference worker
+// The loaded model lives on a dedicated thread and commands talk to it through a bounded
+// request queue, so loading and transcribing never run on the async runtime or a command
+// thread. Requests run one at a time in the order they were submitted.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use tokio::sync::oneshot;
+use crate::diarization::{self, DiarizationConfig};
+use crate::error::NotlokError;
+use crate::hallucination::HallucinationFilter;
+use crate::jobs::Job;
+use crate::punctuation::{self, PunctuationConfig};
+use crate::transcription::{Task, TranscriberModel, Transcript, TranscriptionOptions};
+use crate::vad::{self, VadConfig};
+use crate::vocabulary::{self, Replacement};
+
+// Submitting beyond this many waiting requests fails with Busy
+const QUEUE_LIMIT: usize = 8;
+
+type Loader = Box<dyn FnOnce() -> Result<TranscriberModel, NotlokError> + Send>;
+
+/// Everything a transcription needs, captured when it is submitted
+pub struct TranscriptionRequest {
+    pub job: Job,
+    pub task: Task,
+    pub audio: Vec<f32>,
+    pub vad_config: VadConfig,
+    pub options: TranscriptionOptions,
+    pub replacements: Vec<Replacement>,
+    pub hallucination_filter: HallucinationFilter,
+    pub punctuation_config: PunctuationConfig,
+    pub diarization_config: DiarizationConfig,
+    pub on_progress: Box<dyn FnMut(Task, f64, f64) + Send>, // task, seconds done, total seconds
+}
+
+enum Request {
+    Load {
+        load: Loader,
+        reply: oneshot::Sender<Result<(), NotlokError>>,
+    },
+    Transcribe {
+        request: Box<TranscriptionRequest>,
+        reply: oneshot::Sender<Result<Transcript, NotlokError>>,
+    },
+}
+
+pub struct InferenceWorker {
+    requests: SyncSender<Request>,
+}
+
+fn worker_stopped() -> NotlokError {
+    NotlokError::internal("Inference worker stopped")
+}
+
+impl InferenceWorker {
+    pub fn spawn() -> Self {
+        let (requests, receiver) = sync_channel(QUEUE_LIMIT);
+        std::thread::Builder::new()
+            .name("inference".to_string())
+            .spawn(move || run(receiver))
+            .expect("Failed to spawn inference worker");
+        Self { requests }
+    }
+
+    /// Replaces the loaded model once `load` succeeds, the old one stays on failure
+    pub async fn load(
+        &self,
+        load: impl FnOnce() -> Result<TranscriberModel, NotlokError> + Send + 'static,
+    ) -> Result<(), NotlokError> {
+        let (reply, response) = oneshot::channel();
+        self.submit(Request::Load { load: Box::new(load), reply })?;
+        response.await.map_err(|_| worker_stopped())?
+    }
+
+    pub async fn transcribe(&self, request: TranscriptionRequest) -> Result<Transcript, NotlokError> {
+        let (reply, response) = oneshot::channel();
+        self.submit(Request::Transcribe { request: Box::new(request), reply })?;
+        response.await.map_err(|_| worker_stopped())?
+    }
+
+    fn submit(&self, request: Request) -> Result<(), NotlokError> {
+        self.requests.try_send(request).map_err(|e| match e {
+            TrySendError::Full(_) => NotlokError::Busy,
+            TrySendError::Disconnected(_) => worker_stopped(),
+        })
+    }
+}
+
+fn run(requests: Receiver<Request>) {
+    let mut model: Option<TranscriberModel> = None;
+
+    for request in requests {
+        match request {
+            Request::Load { load, reply } => {
+                let result = catch_panic(load).map(|loaded| model = Some(loaded));
+                let _ = reply.send(result);
+            }
+            Request::Transcribe { request, reply } => {
+                let result = match model.as_mut() {
+                    Some(model) => catch_panic(|| transcribe(model, *request)),
+                    None => Err(NotlokError::NoModelLoaded),
+                };
+                let _ = reply.send(result);
+            }
+        }
+    }
+}
+
+// A panicking request fails on its own instead of taking the worker down with it
+fn catch_panic<T>(f: impl FnOnce() -> Result<T, NotlokError>) -> Result<T, NotlokError> {
+    panic::catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or_else(|_| Err(NotlokError::internal("Inference panicked")))
+}
+
+// Runs VAD over the buffer, transcribes only the speech regions, restores punctuation for
+// engines that don't produce it and labels the speakers.
+// Translating also keeps the original transcript, the English one is attached to it.
+fn transcribe(model: &mut TranscriberModel, mut request: TranscriptionRequest) -> Result<Transcript, NotlokError> {
+    // Cancelled while still queued
+    request.job.token.check()?;
+
+    let audio = &request.audio;
+    let regions = vad::detect_speech(audio, &request.vad_config)?;
+    let cancel = &request.job.token;
+    let on_progress = &mut request.on_progress;
+
+    let mut options = request.options.clone();
+    options.task = Task::Transcribe;
+    let filter = &request.hallucination_filter;
+    let mut transcript = model.transcribe_regions(audio, &regions, &options, filter, cancel, |done, total| {
+        on_progress(Task::Transcribe, done, total)
+    })?;
+    if !matches!(model, TranscriberModel::Whisper(_)) {
+        // Unpunctuated text is still better than none
+        match punctuation::restore(&mut transcript.segments, &request.punctuation_config) {
+            Ok(()) => transcript = Transcript::from_segments(transcript.segments),
+            Err(e) => eprintln!("Punctuation failed: {}", e),
+        }
+    }
+    let translation = if request.task == Task::Translate {
+        options.task = Task::Translate;
+        Some(model.transcribe_regions(audio, &regions, &options, filter, cancel, |done, total| {
+            on_progress(Task::Translate, done, total)
+        })?)
+    } else {
+        None
+    };
+
+    let mut transcript = vocabulary::apply_replacements(transcript, &request.replacements);
+    let translation = translation.map(|t| vocabulary::apply_replacements(t, &request.replacements));
+
+    // A failed diarization shouldn't cost the user the transcript
+    if let Err(e) = diarization::diarize(audio, &mut transcript.segments, &request.diarization_config) {
+        eprintln!("Diarization failed: {}", e);
+    }
+    transcript.translation = translation.map(|mut translation| {
+        diarization::copy_speakers(&transcript.segments, &mut translation.segments);
+        Box::new(translation)
+    });
+    Ok(transcript)
+}
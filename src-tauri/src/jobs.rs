@@ -0,0 +1,78 @@
+// Transcription jobs
+// Every transcription gets an id and a cancel token. The frontend learns the id from the
+// transcription-started event and can cancel with it; the token is polled between chunks
+// and by whisper's abort callback in the middle of one.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use crate::error::NotlokError;
+
+pub type JobId = u64;
+
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn check(&self) -> Result<(), NotlokError> {
+        if self.is_cancelled() {
+            Err(NotlokError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// For C callbacks, valid as long as this token (or a clone) is alive
+    pub fn as_ptr(&self) -> *const AtomicBool {
+        Arc::as_ptr(&self.0)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Jobs {
+    next_id: Arc<AtomicU64>,
+    active: Arc<Mutex<HashMap<JobId, CancelToken>>>,
+}
+
+/// Registered until dropped
+pub struct Job {
+    pub id: JobId,
+    pub token: CancelToken,
+    jobs: Jobs,
+}
+
+impl Jobs {
+    pub fn start(&self) -> Result<Job, NotlokError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let token = CancelToken::default();
+        self.active.lock()?.insert(id, token.clone());
+        Ok(Job {
+            id,
+            token,
+            jobs: self.clone(),
+        })
+    }
+
+    pub fn cancel(&self, id: JobId) -> Result<(), NotlokError> {
+        let active = self.active.lock()?;
+        let token = active.get(&id).ok_or(NotlokError::JobNotFound { job_id: id })?;
+        token.cancel();
+        Ok(())
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        if let Ok(mut active) = self.jobs.active.lock() {
+            active.remove(&self.id);
+        }
+    }
+}
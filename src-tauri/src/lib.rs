@@ -1,20 +1,54 @@
+mod ai;
 mod audio_capture;
+mod chunking;
+mod diarization;
+pub mod error;
+mod hallucination;
+mod history;
+mod i18n;
+mod inference;
+mod jobs;
+mod license;
+mod punctuation;
+mod report;
+mod secrets;
+mod session;
+mod settings;
+mod summarize;
 mod transcription;
+mod vad;
+mod vocabulary;
 
-use audio_capture::{AudioRecorder, AudioDevice};
-use transcription::{TranscriberModel, WhisperTranscriber, ParakeetTranscriber};
+// The capture ring as it ships, for benches/capture_callback.rs
+#[doc(hidden)]
+pub mod bench {
+    pub use crate::audio_capture::ring::{channel, CaptureSink, SourceReader};
+}
+
+use ai::{AiClient, AiConfig, ProviderKind};
+use audio_capture::{AudioRecorder, AudioDevice, AudioStats, AutoPauseConfig, CaptureError, RecorderState, RecordingPauseEvent};
+use diarization::DiarizationConfig;
+use error::NotlokError;
+use hallucination::HallucinationFilter;
+use history::{History, SavedMeetingReport, SavedReport, SessionActionItem, SessionRecord};
+use i18n::{Locale, Message};
+use inference::{InferenceWorker, TranscriptionRequest};
+use jobs::{JobId, Jobs};
+use license::{LicenseInfo, Validation};
+use punctuation::PunctuationConfig;
+use secrets::Secrets;
+use session::SessionMetadata;
+use settings::Settings;
+use summarize::{ReportTemplate, SummarizerConfig};
+use transcription::{TranscriberModel, WhisperTranscriber, ParakeetTranscriber, Task, Transcript, TranscriptionOptions};
+use vad::{VadConfig, VadEngine};
+use vocabulary::Replacement;
+use std::collections::HashMap;
 use std::sync::Mutex;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tauri::{State, AppHandle, Manager, Emitter};
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Serialize)]
-pub struct AudioStats {
-    pub average: f32,
-    pub peak: f32,
-    pub silence: f32,
-}
-
 // FFI declarations for permission checks (macOS)
 #[cfg(target_os = "macos")]
 extern "C" {
@@ -29,13 +63,60 @@ pub struct PermissionStatus {
     pub microphone: String, // "granted", "denied", "not_determined"
 }
 
+#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityTier {
+    Fast,
+    Balanced,
+    Good,
+    VeryGood,
+    Best,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct ModelInfo {
     pub id: String,
     pub name: String,
-    pub size: String,
-    pub quality: String,
+    pub size_bytes: u64,
+    pub quality: QualityTier,
+    pub multilingual: bool,
     pub url: String,
+    pub downloaded: bool, // filled in by list_models
+}
+
+#[derive(Clone, Serialize)]
+pub struct TranscriptionStarted {
+    pub job_id: JobId,
+    pub task: Task,
+    pub session_id: String, // the transcript is saved under it, reports are generated with it
+}
+
+#[derive(Clone, Serialize)]
+pub struct TranscriptionProgress {
+    pub job_id: JobId,
+    pub task: Task,
+    pub processed_seconds: f64, // of speech, silence skipped by VAD isn't counted
+    pub total_seconds: f64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ReportStarted {
+    pub job_id: JobId,
+    pub session_id: String,
+    pub provider: ProviderKind,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ReportProgress {
+    pub job_id: JobId,
+    pub completed_requests: usize, // long transcripts take one request per part plus one
+    pub total_requests: usize,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ReportToken {
+    pub job_id: JobId,
+    pub token: String,
 }
 
 #[derive(Clone, Serialize)]
@@ -46,68 +127,47 @@ pub struct DownloadProgress {
     pub total: u64,
 }
 
+fn model(id: &str, name: &str, size_bytes: u64, quality: QualityTier, multilingual: bool, url: &str) -> ModelInfo {
+    ModelInfo {
+        id: id.to_string(),
+        name: name.to_string(),
+        size_bytes,
+        quality,
+        multilingual,
+        url: url.to_string(),
+        downloaded: false,
+    }
+}
+
 fn get_available_models() -> Vec<ModelInfo> {
     vec![
         // Whisper models
-        ModelInfo {
-            id: "whisper-tiny".to_string(),
-            name: "Whisper Tiny".to_string(),
-            size: "75 MB".to_string(),
-            quality: "Hızlı, düşük kalite".to_string(),
-            url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-tiny.bin".to_string(),
-        },
-        ModelInfo {
-            id: "whisper-base".to_string(),
-            name: "Whisper Base".to_string(),
-            size: "142 MB".to_string(),
-            quality: "Dengeli".to_string(),
-            url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-base.bin".to_string(),
-        },
-        ModelInfo {
-            id: "whisper-small".to_string(),
-            name: "Whisper Small".to_string(),
-            size: "466 MB".to_string(),
-            quality: "İyi kalite".to_string(),
-            url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-small.bin".to_string(),
-        },
-        ModelInfo {
-            id: "whisper-medium".to_string(),
-            name: "Whisper Medium".to_string(),
-            size: "1.5 GB".to_string(),
-            quality: "Çok iyi kalite".to_string(),
-            url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-medium.bin".to_string(),
-        },
-        ModelInfo {
-            id: "whisper-large-v3".to_string(),
-            name: "Whisper Large V3".to_string(),
-            size: "3.1 GB".to_string(),
-            quality: "En iyi kalite".to_string(),
-            url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-large-v3.bin".to_string(),
-        },
+        model("whisper-tiny", "Whisper Tiny", 77_691_713, QualityTier::Fast, true,
+            "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-tiny.bin"),
+        model("whisper-base", "Whisper Base", 147_951_465, QualityTier::Balanced, true,
+            "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-base.bin"),
+        model("whisper-small", "Whisper Small", 487_601_967, QualityTier::Good, true,
+            "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-small.bin"),
+        model("whisper-medium", "Whisper Medium", 1_533_763_059, QualityTier::VeryGood, true,
+            "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-medium.bin"),
+        model("whisper-large-v3", "Whisper Large V3", 3_095_033_483, QualityTier::Best, true,
+            "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-large-v3.bin"),
         // Parakeet Models
-        ModelInfo {
-            id: "parakeet-ctc-0.6b".to_string(),
-            name: "Parakeet CTC 0.6B (English)".to_string(),
-            size: "360 MB".to_string(),
-            quality: "Hızlı, İngilizce".to_string(),
-            url: "https://github.com/k2-fsa/sherpa-onnx/releases/download/v1.9.23/sherpa-onnx-nemo-parakeet-ctc-0.6b-en-2024-03-04.tar.bz2".to_string(),
-        },
-        ModelInfo {
-            id: "parakeet-tdt-0.6b".to_string(),
-            name: "Parakeet TDT 0.6B (Multilingual)".to_string(),
-            size: "380 MB".to_string(),
-            quality: "Çok Dilli, İyi Kalite".to_string(),
-            url: "https://github.com/k2-fsa/sherpa-onnx/releases/download/v1.9.23/sherpa-onnx-nemo-parakeet-tdt-0.6b-v3-int8.tar.bz2".to_string(),
-        },
+        model("parakeet-ctc-0.6b", "Parakeet CTC 0.6B", 360_000_000, QualityTier::Fast, false,
+            "https://github.com/k2-fsa/sherpa-onnx/releases/download/v1.9.23/sherpa-onnx-nemo-parakeet-ctc-0.6b-en-2024-03-04.tar.bz2"),
+        model("parakeet-tdt-0.6b", "Parakeet TDT 0.6B", 380_000_000, QualityTier::Good, true,
+            "https://github.com/k2-fsa/sherpa-onnx/releases/download/v1.9.23/sherpa-onnx-nemo-parakeet-tdt-0.6b-v3-int8.tar.bz2"),
     ]
 }
 
 pub struct AppState {
     recorder: AudioRecorder,
-    transcriber: Mutex<Option<TranscriberModel>>,
+    inference: InferenceWorker, // owns the loaded model
+    jobs: Jobs,
     current_model: Mutex<Option<String>>,
     language: Mutex<String>,
-    recording_start_time: Mutex<Option<std::time::Instant>>,
+    settings: Mutex<Settings>, // loaded from disk in setup
+    locale: Mutex<Locale>, // UI language, for the few messages built in Rust
     has_premium_license: Mutex<bool>,
 }
 
@@ -115,6 +175,10 @@ fn get_models_dir(app: &AppHandle) -> PathBuf {
     app.path().app_data_dir().unwrap().join("models")
 }
 
+fn get_settings_path(app: &AppHandle) -> PathBuf {
+    app.path().app_data_dir().unwrap().join("settings.json")
+}
+
 fn get_model_path(app: &AppHandle, model_id: &str) -> PathBuf {
     let models_dir = get_models_dir(app);
     if model_id.starts_with("whisper-") {
@@ -144,14 +208,8 @@ fn greet(name: &str) -> String {
 
 #[tauri::command]
 fn list_models(app: AppHandle) -> Vec<ModelInfo> {
-    let models = get_available_models();
-    let _models_dir = get_models_dir(&app);
-
-    models.into_iter().map(|mut m| {
-        let path = get_model_path(&app, &m.id);
-        if path.exists() {
-            m.quality = format!("{} (İndirildi)", m.quality);
-        }
+    get_available_models().into_iter().map(|mut m| {
+        m.downloaded = get_model_path(&app, &m.id).exists();
         m
     }).collect()
 }
@@ -162,22 +220,28 @@ fn is_model_downloaded(app: AppHandle, model_id: String) -> bool {
 }
 
 #[tauri::command]
-async fn download_model(app: AppHandle, model_id: String) -> Result<String, String> {
+async fn download_model(app: AppHandle, state: State<'_, AppState>, model_id: String) -> Result<String, NotlokError> {
     let models = get_available_models();
     let model = models.iter().find(|m| m.id == model_id)
-        .ok_or_else(|| "Model bulunamadı".to_string())?;
+        .ok_or_else(|| NotlokError::ModelNotFound { model_id: model_id.clone() })?;
 
     let models_dir = get_models_dir(&app);
-    std::fs::create_dir_all(&models_dir).map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&models_dir)?;
 
     let target_path = get_model_path(&app, &model_id);
 
     if target_path.exists() {
-        return Ok("Model zaten indirilmiş".to_string());
+        return Ok(Message::ModelAlreadyDownloaded.localize(*state.locale.lock()?));
     }
 
     // Download
-    let response = reqwest::get(&model.url).await.map_err(|e| e.to_string())?;
+    let download_failed = |e: reqwest::Error| NotlokError::DownloadFailed {
+        model_id: model_id.clone(),
+        message: e.to_string(),
+    };
+    let response = reqwest::get(&model.url).await
+        .and_then(|r| r.error_for_status())
+        .map_err(download_failed)?;
     let total_size = response.content_length().unwrap_or(0);
 
     // Check if it's an archive
@@ -186,7 +250,7 @@ async fn download_model(app: AppHandle, model_id: String) -> Result<String, Stri
     if is_tar_bz2 {
         // For archives, we download to a temp file first
         let temp_path = models_dir.join(format!("{}.tmp", model_id));
-        let mut file = std::fs::File::create(&temp_path).map_err(|e| e.to_string())?;
+        let mut file = std::fs::File::create(&temp_path)?;
         let mut downloaded: u64 = 0;
         let mut stream = response.bytes_stream();
 
@@ -194,8 +258,8 @@ async fn download_model(app: AppHandle, model_id: String) -> Result<String, Stri
         use std::io::Write;
 
         while let Some(chunk) = stream.next().await {
-            let chunk = chunk.map_err(|e| e.to_string())?;
-            file.write_all(&chunk).map_err(|e| e.to_string())?;
+            let chunk = chunk.map_err(download_failed)?;
+            file.write_all(&chunk)?;
             downloaded += chunk.len() as u64;
 
             let progress = if total_size > 0 {
@@ -212,15 +276,15 @@ async fn download_model(app: AppHandle, model_id: String) -> Result<String, Stri
         }
         
         // Extract
-        let file = std::fs::File::open(&temp_path).map_err(|e| e.to_string())?;
+        let file = std::fs::File::open(&temp_path)?;
         let decoder = bzip2::read::BzDecoder::new(file);
         let mut archive = tar::Archive::new(decoder);
         
         // Extract to models_dir
-        archive.unpack(&models_dir).map_err(|e| e.to_string())?;
+        archive.unpack(&models_dir)?;
         
         // Cleanup temp file
-        std::fs::remove_file(temp_path).map_err(|e| e.to_string())?;
+        std::fs::remove_file(temp_path)?;
         
         // Rename extracted folder to model_id if necessary
         // We know the expected folder names from URLs:
@@ -240,15 +304,15 @@ async fn download_model(app: AppHandle, model_id: String) -> Result<String, Stri
             let final_path = models_dir.join(&model_id);
             if extracted_path.exists() && extracted_path != final_path {
                 if final_path.exists() {
-                    std::fs::remove_dir_all(&final_path).map_err(|e| e.to_string())?;
+                    std::fs::remove_dir_all(&final_path)?;
                 }
-                std::fs::rename(extracted_path, final_path).map_err(|e| e.to_string())?;
+                std::fs::rename(extracted_path, final_path)?;
             }
         }
 
     } else {
         // Single file download (Whisper)
-        let mut file = std::fs::File::create(&target_path).map_err(|e| e.to_string())?;
+        let mut file = std::fs::File::create(&target_path)?;
         let mut downloaded: u64 = 0;
         let mut stream = response.bytes_stream();
 
@@ -256,8 +320,8 @@ async fn download_model(app: AppHandle, model_id: String) -> Result<String, Stri
         use std::io::Write;
 
         while let Some(chunk) = stream.next().await {
-            let chunk = chunk.map_err(|e| e.to_string())?;
-            file.write_all(&chunk).map_err(|e| e.to_string())?;
+            let chunk = chunk.map_err(download_failed)?;
+            file.write_all(&chunk)?;
             downloaded += chunk.len() as u64;
 
             let progress = if total_size > 0 {
@@ -274,210 +338,742 @@ async fn download_model(app: AppHandle, model_id: String) -> Result<String, Stri
         }
     }
 
-    Ok("Model başarıyla indirildi".to_string())
+    Ok(Message::ModelDownloaded.localize(*state.locale.lock()?))
 }
 
 #[tauri::command]
-async fn load_model(app: AppHandle, state: State<'_, AppState>, model_id: String) -> Result<String, String> {
+async fn load_model(app: AppHandle, state: State<'_, AppState>, model_id: String) -> Result<String, NotlokError> {
     let model_path = get_model_path(&app, &model_id);
 
     if !model_path.exists() {
-        return Err("Model indirilmemiş".to_string());
+        return Err(NotlokError::ModelNotDownloaded { model_id });
     }
 
     let path_str = model_path.to_string_lossy().to_string();
-    
-    // Run model loading in a blocking task to avoid blocking the async runtime
-    // Model loading is CPU-intensive and takes time
-    let model_id_clone = model_id.clone();
-    tokio::task::block_in_place(move || {
-        let mut transcriber_guard = state.transcriber.lock().map_err(|e| e.to_string())?;
-
-        if is_whisper_model(&model_id) {
-            let transcriber = WhisperTranscriber::new(&path_str)?;
-            *transcriber_guard = Some(TranscriberModel::Whisper(transcriber));
-        } else if is_parakeet_model(&model_id) {
-            let transcriber = ParakeetTranscriber::new(&path_str)?;
-            *transcriber_guard = Some(TranscriberModel::Parakeet(transcriber));
+
+    let whisper = is_whisper_model(&model_id);
+    if !whisper && !is_parakeet_model(&model_id) {
+        return Err(NotlokError::UnsupportedModel { model_id });
+    }
+
+    // Loading is CPU-intensive and takes time, it runs on the inference worker
+    state.inference.load(move || {
+        if whisper {
+            WhisperTranscriber::new(&path_str).map(TranscriberModel::Whisper)
         } else {
-            return Err("Bilinmeyen model türü".to_string());
+            ParakeetTranscriber::new(&path_str).map(TranscriberModel::Parakeet)
         }
+    }).await?;
 
-        *state.current_model.lock().map_err(|e| e.to_string())? = Some(model_id.clone());
+    *state.current_model.lock()? = Some(model_id.clone());
 
-        Ok(format!("Model yüklendi: {}", model_id_clone))
-    })
+    let locale = *state.locale.lock()?;
+    Ok(Message::ModelLoaded { model_id: &model_id }.localize(locale))
 }
 
 #[tauri::command]
-fn set_language(state: State<'_, AppState>, language: String) -> Result<(), String> {
+fn set_language(state: State<'_, AppState>, language: String) -> Result<(), NotlokError> {
     let valid_languages = ["tr", "en", "auto"];
     if !valid_languages.contains(&language.as_str()) {
-        return Err("Geçersiz dil. tr, en veya auto kullanın".to_string());
+        return Err(NotlokError::InvalidLanguage { language });
     }
-    *state.language.lock().map_err(|e| e.to_string())? = language;
+    *state.language.lock()? = language;
+    Ok(())
+}
+
+#[tauri::command]
+fn set_ui_language(state: State<'_, AppState>, language: Locale) -> Result<(), NotlokError> {
+    *state.locale.lock()? = language;
     Ok(())
 }
 
 #[tauri::command]
-fn get_current_settings(state: State<'_, AppState>) -> Result<(Option<String>, String), String> {
-    let model = state.current_model.lock().map_err(|e| e.to_string())?.clone();
-    let language = state.language.lock().map_err(|e| e.to_string())?.clone();
+fn get_current_settings(state: State<'_, AppState>) -> Result<(Option<String>, String), NotlokError> {
+    let model = state.current_model.lock()?.clone();
+    let language = state.language.lock()?.clone();
     Ok((model, language))
 }
 
 #[tauri::command]
-fn list_audio_input_devices() -> Result<Vec<AudioDevice>, String> {
+fn get_vad_config(state: State<'_, AppState>) -> Result<VadConfig, NotlokError> {
+    Ok(state.settings.lock()?.vad.clone())
+}
+
+#[tauri::command]
+fn set_vad_config(app: AppHandle, state: State<'_, AppState>, config: VadConfig) -> Result<(), NotlokError> {
+    if config.frame_ms == 0 {
+        return Err(NotlokError::InvalidConfig { field: "frame_ms".to_string() });
+    }
+    if config.threshold < 0.0 {
+        return Err(NotlokError::InvalidConfig { field: "threshold".to_string() });
+    }
+    if let VadEngine::Silero { model_path } = &config.engine {
+        // Refused up front rather than failing every transcription later
+        if !cfg!(feature = "onnx") {
+            return Err(NotlokError::InvalidConfig { field: "engine".to_string() });
+        }
+        if !Path::new(model_path).is_file() {
+            return Err(NotlokError::InvalidConfig { field: "model_path".to_string() });
+        }
+    }
+
+    let mut settings = state.settings.lock()?;
+    settings.vad = config;
+    settings.save(&get_settings_path(&app))
+}
+
+#[tauri::command]
+fn get_diarization_config(state: State<'_, AppState>) -> Result<DiarizationConfig, NotlokError> {
+    Ok(state.settings.lock()?.diarization.clone())
+}
+
+#[tauri::command]
+fn set_diarization_config(app: AppHandle, state: State<'_, AppState>, config: DiarizationConfig) -> Result<(), NotlokError> {
+    if config.enabled && !PathBuf::from(&config.model_path).is_file() {
+        return Err(NotlokError::InvalidConfig { field: "model_path".to_string() });
+    }
+    if !(-1.0..=1.0).contains(&config.threshold) {
+        return Err(NotlokError::InvalidConfig { field: "threshold".to_string() });
+    }
+    if config.max_speakers == Some(0) {
+        return Err(NotlokError::InvalidConfig { field: "max_speakers".to_string() });
+    }
+
+    let mut settings = state.settings.lock()?;
+    settings.diarization = config;
+    settings.save(&get_settings_path(&app))
+}
+
+// Falls back to the loaded model when no model is given
+#[tauri::command]
+fn get_transcription_options(state: State<'_, AppState>, model_id: Option<String>) -> Result<TranscriptionOptions, NotlokError> {
+    let model_id = match model_id {
+        Some(id) => Some(id),
+        None => state.current_model.lock()?.clone(),
+    };
+    Ok(state.settings.lock()?.transcription_options(model_id.as_deref()))
+}
+
+#[tauri::command]
+fn set_transcription_options(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    model_id: String,
+    options: TranscriptionOptions,
+) -> Result<(), NotlokError> {
+    if !get_available_models().iter().any(|m| m.id == model_id) {
+        return Err(NotlokError::ModelNotFound { model_id });
+    }
+    if options.beam_size == 0 || options.beam_size > 16 {
+        return Err(NotlokError::InvalidConfig { field: "beam_size".to_string() });
+    }
+    if !(0.0..=1.0).contains(&options.temperature) {
+        return Err(NotlokError::InvalidConfig { field: "temperature".to_string() });
+    }
+    if !(0.0..=1.0).contains(&options.temperature_increment) {
+        return Err(NotlokError::InvalidConfig { field: "temperature_increment".to_string() });
+    }
+    if options.threads == Some(0) {
+        return Err(NotlokError::InvalidConfig { field: "threads".to_string() });
+    }
+    if !(0.0..=1.0).contains(&options.no_speech_threshold) {
+        return Err(NotlokError::InvalidConfig { field: "no_speech_threshold".to_string() });
+    }
+
+    let mut settings = state.settings.lock()?;
+    settings.transcription_options.insert(model_id, options);
+    settings.save(&get_settings_path(&app))
+}
+
+#[tauri::command]
+fn get_vocabulary(state: State<'_, AppState>) -> Result<Vec<String>, NotlokError> {
+    Ok(state.settings.lock()?.vocabulary.clone())
+}
+
+#[tauri::command]
+fn set_vocabulary(app: AppHandle, state: State<'_, AppState>, terms: Vec<String>) -> Result<(), NotlokError> {
+    let mut vocabulary: Vec<String> = Vec::with_capacity(terms.len());
+    for term in terms.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if !vocabulary.iter().any(|v| v == term) {
+            vocabulary.push(term.to_string());
+        }
+    }
+
+    let mut settings = state.settings.lock()?;
+    settings.vocabulary = vocabulary;
+    settings.save(&get_settings_path(&app))
+}
+
+#[tauri::command]
+fn get_replacements(state: State<'_, AppState>) -> Result<Vec<Replacement>, NotlokError> {
+    Ok(state.settings.lock()?.replacements.clone())
+}
+
+#[tauri::command]
+fn set_replacements(app: AppHandle, state: State<'_, AppState>, replacements: Vec<Replacement>) -> Result<(), NotlokError> {
+    if replacements.iter().any(|r| r.find.trim().is_empty()) {
+        return Err(NotlokError::InvalidConfig { field: "find".to_string() });
+    }
+
+    let mut settings = state.settings.lock()?;
+    settings.replacements = replacements;
+    settings.save(&get_settings_path(&app))
+}
+
+#[tauri::command]
+fn get_hallucination_filter(state: State<'_, AppState>) -> Result<HallucinationFilter, NotlokError> {
+    Ok(state.settings.lock()?.hallucination_filter.clone())
+}
+
+#[tauri::command]
+fn set_hallucination_filter(app: AppHandle, state: State<'_, AppState>, mut filter: HallucinationFilter) -> Result<(), NotlokError> {
+    if !filter.min_avg_logprob.is_finite() || filter.min_avg_logprob > 0.0 {
+        return Err(NotlokError::InvalidConfig { field: "min_avg_logprob".to_string() });
+    }
+    if filter.max_repeats == 0 {
+        return Err(NotlokError::InvalidConfig { field: "max_repeats".to_string() });
+    }
+    if filter.max_ngram == 0 {
+        return Err(NotlokError::InvalidConfig { field: "max_ngram".to_string() });
+    }
+    filter.blacklist = filter.blacklist.iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect();
+
+    let mut settings = state.settings.lock()?;
+    settings.hallucination_filter = filter;
+    settings.save(&get_settings_path(&app))
+}
+
+#[tauri::command]
+fn get_punctuation_config(state: State<'_, AppState>) -> Result<PunctuationConfig, NotlokError> {
+    Ok(state.settings.lock()?.punctuation.clone())
+}
+
+#[tauri::command]
+fn set_punctuation_config(app: AppHandle, state: State<'_, AppState>, config: PunctuationConfig) -> Result<(), NotlokError> {
+    if config.enabled && !PathBuf::from(&config.model_path).is_file() {
+        return Err(NotlokError::InvalidConfig { field: "model_path".to_string() });
+    }
+    if config.enabled && !PathBuf::from(&config.vocab_path).is_file() {
+        return Err(NotlokError::InvalidConfig { field: "vocab_path".to_string() });
+    }
+    if config.punctuation_labels.is_empty() {
+        return Err(NotlokError::InvalidConfig { field: "punctuation_labels".to_string() });
+    }
+
+    let mut settings = state.settings.lock()?;
+    settings.punctuation = config;
+    settings.save(&get_settings_path(&app))
+}
+
+#[tauri::command]
+fn get_summarizer_config(state: State<'_, AppState>) -> Result<SummarizerConfig, NotlokError> {
+    Ok(state.settings.lock()?.summarizer.clone())
+}
+
+#[tauri::command]
+fn set_summarizer_config(app: AppHandle, state: State<'_, AppState>, config: SummarizerConfig) -> Result<(), NotlokError> {
+    if !(0.0..=2.0).contains(&config.temperature) {
+        return Err(NotlokError::InvalidConfig { field: "temperature".to_string() });
+    }
+    if config.max_chunk_chars < 1000 {
+        return Err(NotlokError::InvalidConfig { field: "max_chunk_chars".to_string() });
+    }
+    if !config.prompt.contains("{transcript}") {
+        return Err(NotlokError::InvalidConfig { field: "prompt".to_string() });
+    }
+    if !config.map_prompt.contains("{transcript}") {
+        return Err(NotlokError::InvalidConfig { field: "map_prompt".to_string() });
+    }
+    if !config.reduce_prompt.contains("{notes}") {
+        return Err(NotlokError::InvalidConfig { field: "reduce_prompt".to_string() });
+    }
+
+    let mut settings = state.settings.lock()?;
+    settings.summarizer = config;
+    settings.save(&get_settings_path(&app))
+}
+
+#[tauri::command]
+fn get_ai_config(state: State<'_, AppState>) -> Result<AiConfig, NotlokError> {
+    Ok(state.settings.lock()?.ai.clone())
+}
+
+#[tauri::command]
+fn set_ai_config(app: AppHandle, state: State<'_, AppState>, mut config: AiConfig) -> Result<(), NotlokError> {
+    for kind in [ProviderKind::Local, ProviderKind::OpenAi, ProviderKind::Anthropic, ProviderKind::Gemini] {
+        let endpoint = match kind {
+            ProviderKind::Local => &mut config.local,
+            ProviderKind::OpenAi => &mut config.openai,
+            ProviderKind::Anthropic => &mut config.anthropic,
+            ProviderKind::Gemini => &mut config.gemini,
+        };
+        endpoint.base_url = endpoint.base_url.trim().trim_end_matches('/').to_string();
+        if !endpoint.base_url.starts_with("http://") && !endpoint.base_url.starts_with("https://") {
+            return Err(NotlokError::InvalidConfig { field: "base_url".to_string() });
+        }
+        endpoint.model = endpoint.model.take().map(|m| m.trim().to_string()).filter(|m| !m.is_empty());
+    }
+    if config.max_retries > 10 {
+        return Err(NotlokError::InvalidConfig { field: "max_retries".to_string() });
+    }
+    if config.max_output_tokens == 0 {
+        return Err(NotlokError::InvalidConfig { field: "max_output_tokens".to_string() });
+    }
+
+    let mut settings = state.settings.lock()?;
+    settings.ai = config;
+    settings.save(&get_settings_path(&app))
+}
+
+// The instructions a report is written by, also used for reports the frontend requests
+// from the Notlok AI service
+#[tauri::command]
+fn get_report_instructions(state: State<'_, AppState>, template: ReportTemplate, custom_prompt: Option<String>) -> Result<String, NotlokError> {
+    report_instructions(&state, template, custom_prompt)
+}
+
+fn report_instructions(state: &AppState, template: ReportTemplate, custom_prompt: Option<String>) -> Result<String, NotlokError> {
+    let custom_prompt = custom_prompt.filter(|p| !p.trim().is_empty());
+    match (template, custom_prompt) {
+        (ReportTemplate::Custom, Some(prompt)) => Ok(prompt.trim().to_string()),
+        _ => Ok(Message::ReportInstructions(template).localize(*state.locale.lock()?)),
+    }
+}
+
+// Writes a report of a saved session with the given provider, or the default one, and
+// stores it with the session. The job id comes with the report-started event, the answer
+// streams in through report-token events and is also returned whole.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn generate_report(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    history: State<'_, History>,
+    secrets: State<'_, Secrets>,
+    session_id: String,
+    template: ReportTemplate,
+    custom_prompt: Option<String>,
+    provider: Option<ProviderKind>,
+) -> Result<SavedReport, NotlokError> {
+    let mut record = history.load(&session_id)?;
+    let (ai_config, summarizer_config) = {
+        let settings = state.settings.lock()?;
+        (settings.ai.clone(), settings.summarizer.clone())
+    };
+    let kind = provider.unwrap_or(ai_config.default_provider);
+    let api_key = match kind.secret_name() {
+        Some(name) => secrets.get(name)?,
+        None => None,
+    };
+    let client = AiClient::new(ai::provider(kind, &ai_config, api_key)?, ai_config.max_retries, summarizer_config.temperature)?;
+    let instructions = report_instructions(&state, template, custom_prompt)?;
+
+    let job = state.jobs.start()?;
+    let job_id = job.id;
+    let _ = app.emit("report-started", ReportStarted { job_id, session_id, provider: kind });
+
+    let completion = summarize::summarize(
+        &client,
+        &summarizer_config,
+        &instructions,
+        &report::labelled(&record.transcript, &record.speakers),
+        &job.token,
+        |completed_requests, total_requests| {
+            let _ = app.emit("report-progress", ReportProgress { job_id, completed_requests, total_requests });
+        },
+        |token| {
+            let _ = app.emit("report-token", ReportToken { job_id, token: token.to_string() });
+        },
+    )
+    .await?;
+
+    let report = SavedReport {
+        text: completion.text,
+        template,
+        provider: kind,
+        usage: completion.usage,
+        created_at: session::now_millis(),
+    };
+    record.report = Some(report.clone());
+    history.save(&record)?;
+    Ok(report)
+}
+
+// Like generate_report, but asks for the report as JSON with decisions, action items,
+// open questions and topics, checks it and stores it with the session. Progress comes
+// through report-progress events, nothing is streamed.
+#[tauri::command]
+async fn generate_meeting_report(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    history: State<'_, History>,
+    secrets: State<'_, Secrets>,
+    session_id: String,
+    provider: Option<ProviderKind>,
+) -> Result<SavedMeetingReport, NotlokError> {
+    let mut record = history.load(&session_id)?;
+    let (ai_config, summarizer_config) = {
+        let settings = state.settings.lock()?;
+        (settings.ai.clone(), settings.summarizer.clone())
+    };
+    let kind = provider.unwrap_or(ai_config.default_provider);
+    let api_key = match kind.secret_name() {
+        Some(name) => secrets.get(name)?,
+        None => None,
+    };
+    let client = AiClient::new(ai::provider(kind, &ai_config, api_key)?, ai_config.max_retries, summarizer_config.temperature)?;
+    let instructions = Message::MeetingReportInstructions.localize(*state.locale.lock()?);
+    let started_at = record.metadata.as_ref().map_or(record.created_at, |m| m.started_at);
+
+    let job = state.jobs.start()?;
+    let job_id = job.id;
+    let _ = app.emit("report-started", ReportStarted { job_id, session_id, provider: kind });
+
+    let (report, usage) = report::extract(
+        &client,
+        &summarizer_config,
+        &instructions,
+        &record.transcript,
+        &record.speakers,
+        &report::format_date(started_at),
+        &job.token,
+        |completed_requests, total_requests| {
+            let _ = app.emit("report-progress", ReportProgress { job_id, completed_requests, total_requests });
+        },
+    )
+    .await?;
+
+    let saved = SavedMeetingReport {
+        report,
+        provider: kind,
+        usage,
+        created_at: session::now_millis(),
+    };
+    record.meeting_report = Some(saved.clone());
+    history.save(&record)?;
+    Ok(saved)
+}
+
+// Action items from the meeting reports of every saved session
+#[tauri::command]
+fn list_action_items(history: State<'_, History>) -> Result<Vec<SessionActionItem>, NotlokError> {
+    history.action_items()
+}
+
+#[tauri::command]
+fn cancel_report(state: State<'_, AppState>, job_id: JobId) -> Result<(), NotlokError> {
+    state.jobs.cancel(job_id)
+}
+
+#[tauri::command]
+fn delete_session(history: State<'_, History>, session_id: String) -> Result<(), NotlokError> {
+    history.delete(&session_id)
+}
+
+// Names a diarized speaker of a saved session, reports use it instead of "Speaker N".
+// An empty name goes back to the number.
+#[tauri::command]
+fn rename_speaker(history: State<'_, History>, session_id: String, speaker: usize, name: String) -> Result<(), NotlokError> {
+    let mut record = history.load(&session_id)?;
+    if !record.transcript.segments.iter().any(|s| s.speaker == Some(speaker)) {
+        return Err(NotlokError::InvalidConfig { field: "speaker".to_string() });
+    }
+    let name = name.trim();
+    if name.is_empty() {
+        record.speakers.remove(&speaker);
+    } else {
+        record.speakers.insert(speaker, name.to_string());
+    }
+    history.save(&record)
+}
+
+// API keys and the license key, kept out of the webview's storage. The webview can write
+// them but never read them back, only ask whether one is set.
+#[tauri::command]
+fn has_secret(secrets: State<'_, Secrets>, name: String) -> Result<bool, NotlokError> {
+    secrets::validate_name(&name)?;
+    Ok(secrets.get(&name)?.is_some())
+}
+
+#[tauri::command]
+fn set_secret(secrets: State<'_, Secrets>, name: String, value: String) -> Result<(), NotlokError> {
+    secrets::validate_name(&name)?;
+    if value.trim().is_empty() {
+        return secrets.delete(&name);
+    }
+    secrets.set(&name, value.trim())
+}
+
+#[tauri::command]
+fn delete_secret(secrets: State<'_, Secrets>, name: String) -> Result<(), NotlokError> {
+    secrets::validate_name(&name)?;
+    secrets.delete(&name)
+}
+
+#[tauri::command]
+fn list_audio_input_devices() -> Vec<AudioDevice> {
     AudioRecorder::list_input_devices()
 }
 
 #[tauri::command]
-fn list_audio_output_devices() -> Result<Vec<AudioDevice>, String> {
+fn list_audio_output_devices() -> Vec<AudioDevice> {
     AudioRecorder::list_output_devices()
 }
 
 #[tauri::command]
-fn set_input_device(state: State<'_, AppState>, device_id: Option<String>) -> Result<(), String> {
+fn set_input_device(state: State<'_, AppState>, device_id: Option<String>) -> Result<(), NotlokError> {
     state.recorder.set_input_device(device_id);
     Ok(())
 }
 
 #[tauri::command]
-fn get_input_device(state: State<'_, AppState>) -> Result<Option<String>, String> {
+fn get_input_device(state: State<'_, AppState>) -> Result<Option<String>, NotlokError> {
     Ok(state.recorder.get_input_device())
 }
 
 #[tauri::command]
-fn start_recording(state: State<'_, AppState>) -> Result<(), String> {
-    // Start recording timer
-    let mut start_time = state.recording_start_time.lock().map_err(|e| e.to_string())?;
-    *start_time = Some(std::time::Instant::now());
-    
-    state.recorder.start_recording()
+fn set_input_fallback(app: AppHandle, state: State<'_, AppState>, enabled: bool) -> Result<(), NotlokError> {
+    let mut settings = state.settings.lock()?;
+    settings.input.fallback_to_default = enabled;
+    settings.save(&get_settings_path(&app))?;
+    state.recorder.set_fallback_to_default(enabled);
+    Ok(())
 }
 
 #[tauri::command]
-fn get_recording_duration(state: State<'_, AppState>) -> Result<f64, String> {
-    let start_time = state.recording_start_time.lock().map_err(|e| e.to_string())?;
-    
-    if let Some(start) = *start_time {
-        let duration = start.elapsed().as_secs_f64();
-        Ok(duration)
-    } else {
-        Ok(0.0)
+fn get_input_fallback(state: State<'_, AppState>) -> Result<bool, NotlokError> {
+    Ok(state.recorder.get_fallback_to_default())
+}
+
+#[tauri::command]
+fn get_auto_pause_config(state: State<'_, AppState>) -> Result<AutoPauseConfig, NotlokError> {
+    Ok(state.recorder.get_auto_pause_config())
+}
+
+#[tauri::command]
+fn set_auto_pause_config(app: AppHandle, state: State<'_, AppState>, config: AutoPauseConfig) -> Result<(), NotlokError> {
+    let mut settings = state.settings.lock()?;
+    settings.auto_pause = config.clone();
+    settings.save(&get_settings_path(&app))?;
+    state.recorder.set_auto_pause_config(config);
+    Ok(())
+}
+
+#[tauri::command]
+fn start_recording(app: AppHandle, state: State<'_, AppState>) -> Result<(), NotlokError> {
+    // Checked up front, otherwise the capture only fails once the stream is opened
+    #[cfg(target_os = "macos")]
+    {
+        use error::Permission;
+        if !unsafe { sc_check_screen_recording_permission() } {
+            return Err(NotlokError::PermissionDenied { permission: Permission::ScreenRecording });
+        }
+        if unsafe { sc_check_microphone_permission() } < 0 {
+            return Err(NotlokError::PermissionDenied { permission: Permission::Microphone });
+        }
     }
+
+    state.recorder.start_recording(app)
 }
 
 #[tauri::command]
-fn check_recording_limit(state: State<'_, AppState>) -> Result<bool, String> {
+fn pause_recording(app: AppHandle, state: State<'_, AppState>) -> Result<(), NotlokError> {
+    state.recorder.pause_recording()?;
+    let _ = app.emit("recording-paused", RecordingPauseEvent {
+        auto: false,
+        silent_secs: 0.0,
+    });
+    Ok(())
+}
+
+#[tauri::command]
+fn resume_recording(app: AppHandle, state: State<'_, AppState>) -> Result<(), NotlokError> {
+    state.recorder.resume_recording()?;
+    let _ = app.emit("recording-resumed", RecordingPauseEvent {
+        auto: false,
+        silent_secs: 0.0,
+    });
+    Ok(())
+}
+
+#[tauri::command]
+fn is_recording_paused(state: State<'_, AppState>) -> bool {
+    state.recorder.is_paused()
+}
+
+#[tauri::command]
+fn get_session_metadata(state: State<'_, AppState>) -> Option<SessionMetadata> {
+    state.recorder.session_metadata()
+}
+
+#[tauri::command]
+fn get_recording_duration(state: State<'_, AppState>) -> Result<f64, NotlokError> {
+    // Paused time is excluded
+    Ok(state.recorder.recording_duration())
+}
+
+#[tauri::command]
+fn check_recording_limit(state: State<'_, AppState>) -> Result<bool, NotlokError> {
     // Check if user has premium license
-    let has_premium = *state.has_premium_license.lock().map_err(|e| e.to_string())?;
+    let has_premium = *state.has_premium_license.lock()?;
     
     if has_premium {
         return Ok(false); // No limit for premium users
     }
     
     // Free users: 60 seconds limit
-    let start_time = state.recording_start_time.lock().map_err(|e| e.to_string())?;
-    
-    if let Some(start) = *start_time {
-        let duration = start.elapsed().as_secs_f64();
-        Ok(duration >= 60.0) // true if limit reached
-    } else {
-        Ok(false)
-    }
+    let duration = state.recorder.recording_duration();
+    Ok(duration >= 60.0) // true if limit reached
 }
 
 #[tauri::command]
-fn get_audio_buffer_size(state: State<'_, AppState>) -> Result<usize, String> {
-    let buffer = state.recorder.get_audio_buffer();
-    Ok(buffer.len())
+fn get_recorder_state(state: State<'_, AppState>) -> RecorderState {
+    state.recorder.state()
 }
 
 #[tauri::command]
-fn get_audio_stats(state: State<'_, AppState>) -> Result<AudioStats, String> {
-    let buffer = state.recorder.get_audio_buffer();
-    
-    if buffer.is_empty() {
-        return Ok(AudioStats {
-            average: 0.0,
-            peak: 0.0,
-            silence: 100.0,
-        });
-    }
-    
-    // Calculate average absolute value (volume)
-    let sum: f32 = buffer.iter().map(|s| s.abs()).sum();
-    let average = sum / buffer.len() as f32;
-    
-    // Calculate peak
-    let peak = buffer.iter().map(|s| s.abs()).fold(0.0f32, f32::max);
-    
-    // Calculate silence percentage (samples below threshold)
-    let silence_threshold = 0.01f32;
-    let silent_samples = buffer.iter().filter(|s| s.abs() < silence_threshold).count();
-    let silence = (silent_samples as f32 / buffer.len() as f32) * 100.0;
-    
-    Ok(AudioStats {
-        average,
-        peak,
-        silence,
-    })
+fn get_audio_buffer_size(state: State<'_, AppState>) -> Result<usize, NotlokError> {
+    Ok(state.recorder.buffer_len())
 }
 
 #[tauri::command]
-async fn stop_recording_only(state: State<'_, AppState>) -> Result<(), String> {
-    // Reset recording timer
-    let mut start_time = state.recording_start_time.lock().map_err(|e| e.to_string())?;
-    *start_time = None;
-    
-    // Non-blocking stop - just set the flag
-    let _ = state.recorder.stop_recording();
-    Ok(())
+fn get_audio_stats(state: State<'_, AppState>) -> Result<AudioStats, NotlokError> {
+    Ok(state.recorder.audio_stats())
+}
+
+// Joining the capture threads blocks until they drain, so it happens on the blocking pool
+// instead of an async worker
+async fn stop_capture(app: &AppHandle) -> Result<Vec<CaptureError>, NotlokError> {
+    let app = app.clone();
+    tokio::task::spawn_blocking(move || app.state::<AppState>().recorder.stop_recording())
+        .await
+        .map_err(|e| NotlokError::internal(e.to_string()))?
 }
 
 #[tauri::command]
-async fn transcribe_audio(state: State<'_, AppState>) -> Result<String, String> {
-    // Get the recorded audio buffer
-    let audio_data = state.recorder.get_audio_buffer();
+async fn stop_recording_only(app: AppHandle) -> Result<Vec<CaptureError>, NotlokError> {
+    let errors = match stop_capture(&app).await {
+        Ok(errors) => errors,
+        // An auto-stop got there first
+        Err(NotlokError::NotRecording) => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    for e in &errors {
+        eprintln!("{}", e);
+    }
+    Ok(errors)
+}
 
-    let audio_to_use = if audio_data.is_empty() {
-        // Use placeholder if no actual audio recorded yet
-        vec![0.0; 16000 * 5]
-    } else {
-        audio_data
+// Whisper only translates with its multilingual models
+fn supports_translation(model_id: &str) -> bool {
+    is_whisper_model(model_id) && get_available_models().iter().any(|m| m.id == model_id && m.multilingual)
+}
+
+// Queues a transcription of the buffer on the inference worker with the current settings
+async fn submit_transcription(app: &AppHandle, state: &AppState, audio: Vec<f32>, task: Option<Task>) -> Result<Transcript, NotlokError> {
+    let current_model = state.current_model.lock()?.clone();
+    let (options, replacements, vad_config, hallucination_filter, punctuation_config, diarization_config) = {
+        let settings = state.settings.lock()?;
+        let mut options = settings.transcription_options(current_model.as_deref());
+        options.initial_prompt = vocabulary::build_prompt(options.initial_prompt.as_deref(), &settings.vocabulary);
+        (
+            options,
+            settings.replacements.clone(),
+            settings.vad.clone(),
+            settings.hallucination_filter.clone(),
+            settings.punctuation.clone(),
+            settings.diarization.clone(),
+        )
     };
+    let task = task.unwrap_or(options.task);
+    if let Some(model_id) = current_model.clone().filter(|id| task == Task::Translate && !supports_translation(id)) {
+        return Err(NotlokError::UnsupportedModel { model_id });
+    }
 
-    // Run transcription in a blocking context to avoid blocking the async runtime
-    tokio::task::block_in_place(|| {
-        let mut transcriber = state.transcriber.lock().map_err(|e| e.to_string())?;
-        if let Some(t) = transcriber.as_mut() {
-            return t.transcribe(&audio_to_use);
-        }
-        Ok("Kayıt durduruldu. Model yüklenmemiş, transkript yok.".to_string())
-    })
+    // Saved under the recording session, audio from elsewhere gets an id of its own
+    let metadata = state.recorder.session_metadata();
+    let session_id = metadata.as_ref().map_or_else(|| session::now_millis().to_string(), |m| m.id.clone());
+    let language = state.language.lock()?.clone();
+
+    let job = state.jobs.start()?;
+    let job_id = job.id;
+    let _ = app.emit("transcription-started", TranscriptionStarted { job_id, task, session_id: session_id.clone() });
+
+    let progress_app = app.clone();
+    let request = TranscriptionRequest {
+        job,
+        task,
+        audio,
+        vad_config,
+        options,
+        replacements,
+        hallucination_filter,
+        punctuation_config,
+        diarization_config,
+        on_progress: Box::new(move |task, processed_seconds, total_seconds| {
+            let _ = progress_app.emit("transcription-progress", TranscriptionProgress {
+                job_id,
+                task,
+                processed_seconds,
+                total_seconds,
+            });
+        }),
+    };
+    let transcript = state.inference.transcribe(request).await?;
+
+    // A new transcript replaces the session's old one along with its report
+    let record = SessionRecord {
+        id: session_id,
+        created_at: session::now_millis(),
+        model: current_model,
+        language,
+        metadata,
+        transcript: transcript.clone(),
+        speakers: HashMap::new(),
+        report: None,
+        meeting_report: None,
+    };
+    if let Err(e) = app.state::<History>().save(&record) {
+        eprintln!("Failed to save session {}: {}", record.id, e);
+    }
+    Ok(transcript)
+}
+
+// The recorded buffer, or what each source delivered when there is nothing to transcribe
+fn recorded_audio(state: &AppState) -> Result<Vec<f32>, NotlokError> {
+    let audio_data = state.recorder.get_audio_buffer();
+    if audio_data.is_empty() {
+        return Err(NotlokError::NoAudioCaptured {
+            sources: state.recorder.capture_diagnostics(),
+        });
+    }
+    Ok(audio_data)
 }
 
 #[tauri::command]
-fn stop_recording(state: State<'_, AppState>) -> Result<String, String> {
-    state.recorder.stop_recording()?;
+fn cancel_transcription(state: State<'_, AppState>, job_id: JobId) -> Result<(), NotlokError> {
+    state.jobs.cancel(job_id)
+}
 
-    // Get the recorded audio buffer
-    let audio_data = state.recorder.get_audio_buffer();
+#[tauri::command]
+async fn transcribe_audio(app: AppHandle, state: State<'_, AppState>, task: Option<Task>) -> Result<Transcript, NotlokError> {
+    let audio_data = recorded_audio(&state)?;
+    submit_transcription(&app, &state, audio_data, task).await
+}
 
-    let audio_to_use = if audio_data.is_empty() {
-        // Use placeholder if no actual audio recorded yet
-        vec![0.0; 16000 * 5]
-    } else {
-        audio_data
-    };
+#[tauri::command]
+async fn stop_recording(app: AppHandle, state: State<'_, AppState>, task: Option<Task>) -> Result<Transcript, NotlokError> {
+    let errors = stop_capture(&app).await?;
 
-    let mut transcriber = state.transcriber.lock().map_err(|e| e.to_string())?;
-    if let Some(t) = transcriber.as_mut() {
-        return t.transcribe(&audio_to_use);
+    if state.recorder.buffer_len() == 0 && !errors.is_empty() {
+        return Err(NotlokError::CaptureFailed { errors });
     }
 
-    Ok("Kayıt durduruldu. Model yüklenmemiş, transkript yok.".to_string())
+    let audio_data = recorded_audio(&state)?;
+    submit_transcription(&app, &state, audio_data, task).await
 }
 
 #[tauri::command]
@@ -537,32 +1133,60 @@ fn open_microphone_preferences() {
     }
 }
 
+/// Activates the key with the license server and keeps it in the keychain
 #[tauri::command]
-fn set_premium_license(state: State<'_, AppState>, license_key: String) -> Result<bool, String> {
-    // Simple validation - in production, this should validate against your license server
-    // For now, we'll just check if license_key is not empty
-    let is_valid = !license_key.trim().is_empty();
-    
-    if is_valid {
-        let mut has_premium = state.has_premium_license.lock().map_err(|e| e.to_string())?;
-        *has_premium = true;
-        Ok(true)
-    } else {
-        Ok(false)
-    }
+async fn activate_license(
+    state: State<'_, AppState>,
+    secrets: State<'_, Secrets>,
+    license_key: String,
+    email: String,
+    instance_name: String,
+) -> Result<LicenseInfo, NotlokError> {
+    let key = license_key.trim();
+    let info = license::activate(key, email.trim(), &instance_name).await?;
+    secrets.set(license::SECRET_NAME, key)?;
+    *state.has_premium_license.lock()? = true;
+    Ok(info)
 }
 
+/// Checks the saved license with the server. None when there is none or it was rejected,
+/// in which case it is removed.
 #[tauri::command]
-fn check_premium_license(state: State<'_, AppState>) -> Result<bool, String> {
-    let has_premium = *state.has_premium_license.lock().map_err(|e| e.to_string())?;
+async fn validate_license(state: State<'_, AppState>, secrets: State<'_, Secrets>) -> Result<Option<LicenseInfo>, NotlokError> {
+    let Some(key) = secrets.get(license::SECRET_NAME)? else {
+        *state.has_premium_license.lock()? = false;
+        return Ok(None);
+    };
+    let info = match license::validate(&key).await {
+        Validation::Valid(info) => Some(info),
+        Validation::Invalid => {
+            secrets.delete(license::SECRET_NAME)?;
+            None
+        }
+        // Offline use keeps working with a license that was valid before
+        Validation::Unreachable => Some(LicenseInfo::offline(&key)),
+    };
+    *state.has_premium_license.lock()? = info.is_some();
+    Ok(info)
+}
+
+/// Sends a report prompt to the Notlok AI service, signed with the saved license
+#[tauri::command]
+async fn notlok_ai_request(secrets: State<'_, Secrets>, email: String, prompt: String) -> Result<serde_json::Value, NotlokError> {
+    let key = secrets.get(license::SECRET_NAME)?.ok_or(NotlokError::LicenseInvalid)?;
+    license::notlok_ai(&key, &email, &prompt).await
+}
+
+#[tauri::command]
+fn check_premium_license(state: State<'_, AppState>) -> Result<bool, NotlokError> {
+    let has_premium = *state.has_premium_license.lock()?;
     Ok(has_premium)
 }
 
 #[tauri::command]
-fn remove_premium_license(state: State<'_, AppState>) -> Result<(), String> {
-    let mut has_premium = state.has_premium_license.lock().map_err(|e| e.to_string())?;
-    *has_premium = false;
-    Ok(())
+fn remove_premium_license(state: State<'_, AppState>, secrets: State<'_, Secrets>) -> Result<(), NotlokError> {
+    *state.has_premium_license.lock()? = false;
+    secrets.delete(license::SECRET_NAME)
 }
 
 #[tauri::command]
@@ -656,31 +1280,65 @@ fn is_ip_address(s: &str) -> bool {
 pub fn run() {
     let app_state = AppState {
         recorder: AudioRecorder::new(),
-        transcriber: Mutex::new(None),
+        inference: InferenceWorker::spawn(),
+        jobs: Jobs::default(),
         current_model: Mutex::new(None),
         language: Mutex::new("tr".to_string()),
-        recording_start_time: Mutex::new(None),
+        settings: Mutex::new(Settings::default()),
+        locale: Mutex::new(Locale::default()),
         has_premium_license: Mutex::new(false),
     };
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .manage(app_state)
+        .setup(|app| {
+            let settings = Settings::load(&get_settings_path(app.handle()));
+            let state = app.state::<AppState>();
+            // The recorder keeps its own copies, read by the capture threads
+            state.recorder.set_auto_pause_config(settings.auto_pause.clone());
+            state.recorder.set_fallback_to_default(settings.input.fallback_to_default);
+            *state.settings.lock().unwrap() = settings;
+            let data_dir = app.path().app_data_dir()?;
+            let secrets = Secrets::new(&data_dir);
+            // A saved license counts until validate_license hears otherwise from the server
+            let has_license = secrets.get(license::SECRET_NAME).unwrap_or_else(|e| {
+                eprintln!("Failed to read the license key: {}", e);
+                None
+            });
+            *state.has_premium_license.lock().unwrap() = has_license.is_some();
+            app.manage(secrets);
+            app.manage(History::new(&data_dir));
+            audio_capture::spawn_device_watcher(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             list_audio_input_devices,
             list_audio_output_devices,
             set_input_device,
             get_input_device,
+            set_input_fallback,
+            get_input_fallback,
+            get_auto_pause_config,
+            set_auto_pause_config,
             start_recording,
+            pause_recording,
+            resume_recording,
+            is_recording_paused,
+            get_session_metadata,
             stop_recording,
             stop_recording_only,
             transcribe_audio,
+            cancel_transcription,
+            get_recorder_state,
             get_audio_buffer_size,
             get_audio_stats,
             get_recording_duration,
             check_recording_limit,
-            set_premium_license,
+            activate_license,
+            validate_license,
+            notlok_ai_request,
             check_premium_license,
             remove_premium_license,
             list_models,
@@ -688,7 +1346,36 @@ pub fn run() {
             download_model,
             load_model,
             set_language,
+            set_ui_language,
             get_current_settings,
+            get_vad_config,
+            set_vad_config,
+            get_diarization_config,
+            set_diarization_config,
+            get_transcription_options,
+            set_transcription_options,
+            get_vocabulary,
+            set_vocabulary,
+            get_replacements,
+            set_replacements,
+            get_hallucination_filter,
+            set_hallucination_filter,
+            get_punctuation_config,
+            set_punctuation_config,
+            get_summarizer_config,
+            set_summarizer_config,
+            get_ai_config,
+            set_ai_config,
+            get_report_instructions,
+            generate_report,
+            generate_meeting_report,
+            list_action_items,
+            cancel_report,
+            delete_session,
+            rename_speaker,
+            has_secret,
+            set_secret,
+            delete_secret,
             check_permissions,
             request_screen_permission,
             open_system_preferences,
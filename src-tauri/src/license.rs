@@ -0,0 +1,159 @@
+// Premium license
+// Keys are sold through Lemon Squeezy and checked against its license API here, not in the
+// webview. The key itself lives in `Secrets`; the frontend types it in once and afterwards
+// only sees whether there is a valid license and a hint of which one it is.
+
+use std::time::Duration;
+use serde::Serialize;
+use serde_json::Value;
+use crate::error::NotlokError;
+
+/// Name of the license key in `Secrets`
+pub const SECRET_NAME: &str = "license-key";
+
+const LICENSE_API: &str = "https://api.lemonsqueezy.com/v1/licenses";
+const NOTLOK_AI_URL: &str = "https://notlok.app/api/webhook";
+const EXPECTED_STORE_ID: u64 = 53624;
+const EXPECTED_PRODUCT_ID: u64 = 699528;
+const LICENSE_TIMEOUT: Duration = Duration::from_secs(15);
+const NOTLOK_AI_TIMEOUT: Duration = Duration::from_secs(180); // a whole report is written before it answers
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LicenseInfo {
+    pub valid: bool,
+    pub key_hint: String, // first 8 and last 4 characters of the key
+    pub status: String,
+    pub customer_email: String,
+    pub activation_usage: u64,
+    pub activation_limit: u64,
+    pub expires_at: Option<String>,
+}
+
+impl LicenseInfo {
+    /// What is known without the server, for a key that validated before
+    pub fn offline(key: &str) -> Self {
+        Self {
+            valid: true,
+            key_hint: key_hint(key),
+            status: "active".to_string(),
+            customer_email: String::new(),
+            activation_usage: 0,
+            activation_limit: 0,
+            expires_at: None,
+        }
+    }
+}
+
+pub enum Validation {
+    Valid(LicenseInfo),
+    Invalid,     // the server rejected the key, it should be forgotten
+    Unreachable, // no answer, the key is trusted as before
+}
+
+fn key_hint(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() <= 12 {
+        return "*".repeat(chars.len());
+    }
+    let head: String = chars[..8].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}...{}", head, tail)
+}
+
+fn server_error(e: impl std::fmt::Display) -> NotlokError {
+    NotlokError::LicenseServer { message: e.to_string() }
+}
+
+fn client(timeout: Duration) -> Result<reqwest::Client, NotlokError> {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|e| NotlokError::internal(e.to_string()))
+}
+
+// Lemon Squeezy answers errors with a JSON body and a 4xx status, both are read the same way
+async fn post(action: &str, form: &[(&str, &str)]) -> Result<Value, NotlokError> {
+    client(LICENSE_TIMEOUT)?
+        .post(format!("{}/{}", LICENSE_API, action))
+        .form(form)
+        .send()
+        .await
+        .map_err(server_error)?
+        .json()
+        .await
+        .map_err(server_error)
+}
+
+// Keys of other stores or products are valid keys, just not for Notlok
+fn license_info(data: &Value) -> Result<LicenseInfo, NotlokError> {
+    let meta = &data["meta"];
+    if meta["store_id"].as_u64() != Some(EXPECTED_STORE_ID) || meta["product_id"].as_u64() != Some(EXPECTED_PRODUCT_ID) {
+        return Err(NotlokError::LicenseInvalid);
+    }
+    let license = &data["license_key"];
+    Ok(LicenseInfo {
+        valid: true,
+        key_hint: key_hint(license["key"].as_str().unwrap_or_default()),
+        status: license["status"].as_str().unwrap_or_default().to_string(),
+        customer_email: meta["customer_email"].as_str().unwrap_or_default().to_string(),
+        activation_usage: license["activation_usage"].as_u64().unwrap_or_default(),
+        activation_limit: license["activation_limit"].as_u64().unwrap_or_default(),
+        expires_at: license["expires_at"].as_str().map(String::from),
+    })
+}
+
+/// Activates `key` for this computer. The license has to belong to `email`.
+pub async fn activate(key: &str, email: &str, instance_name: &str) -> Result<LicenseInfo, NotlokError> {
+    let data = post("activate", &[("license_key", key), ("instance_name", instance_name)]).await?;
+    if data["activated"].as_bool() != Some(true) {
+        let error = data["error"].as_str().unwrap_or_default();
+        if error.contains("activation") {
+            return Err(NotlokError::ActivationLimitReached);
+        }
+        return Err(server_error(error));
+    }
+    let info = license_info(&data)?;
+    if !info.customer_email.eq_ignore_ascii_case(email) {
+        return Err(NotlokError::LicenseEmailMismatch);
+    }
+    Ok(info)
+}
+
+/// Checks a saved key with the server
+pub async fn validate(key: &str) -> Validation {
+    let data = match post("validate", &[("license_key", key)]).await {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("License validation failed: {}", e);
+            return Validation::Unreachable;
+        }
+    };
+    if data["valid"].as_bool() != Some(true) {
+        return Validation::Invalid;
+    }
+    match license_info(&data) {
+        Ok(info) => Validation::Valid(info),
+        Err(_) => Validation::Invalid,
+    }
+}
+
+/// The answer of a Notlok AI report request, which authenticates with the license key
+pub async fn notlok_ai(key: &str, email: &str, prompt: &str) -> Result<Value, NotlokError> {
+    let response = client(NOTLOK_AI_TIMEOUT)?
+        .post(NOTLOK_AI_URL)
+        // The service takes its parameters in the query string
+        .query(&[("license_key", key), ("email", email), ("prompt", prompt)])
+        .send()
+        .await
+        .map_err(server_error)?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(NotlokError::NotlokAiFailed {
+            status: status.as_u16(),
+            message: if body.trim().is_empty() { status.to_string() } else { body.trim().to_string() },
+        });
+    }
+    response.json().await.map_err(server_error)
+}
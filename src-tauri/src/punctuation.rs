@@ -0,0 +1,231 @@
+// Punctuation and casing restoration
+// CTC engines like Parakeet return lowercase text without punctuation. When enabled, a small
+// on-device ONNX model puts the punctuation and capitals back so their transcripts read
+// like Whisper's. Whisper output already has both and never goes through this.
+//
+// The model is expected to be a token classifier like NeMo's punctuation_capitalization
+// export: `input_ids` and `attention_mask` (plus `token_type_ids` if it asks for them)
+// shaped [1, tokens], returning punctuation logits then capitalization logits, each
+// shaped [1, tokens, labels]. Words are split into WordPiece tokens with the model's
+// vocab.txt and labelled by their first token.
+
+use serde::{Deserialize, Serialize};
+use crate::error::NotlokError;
+use crate::transcription::TranscriptSegment;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PunctuationConfig {
+    pub enabled: bool,
+    pub model_path: String,
+    pub vocab_path: String,              // WordPiece vocabulary, one token per line
+    pub punctuation_labels: Vec<String>, // in the order of the model's output, "" for none
+}
+
+impl Default for PunctuationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model_path: String::new(),
+            vocab_path: String::new(),
+            punctuation_labels: ["", ",", ".", "?"].map(String::from).to_vec(),
+        }
+    }
+}
+
+// BERT-style models take at most 512 tokens, text is labelled in windows well below that
+#[cfg(feature = "onnx")]
+const MAX_WINDOW_TOKENS: usize = 256;
+
+#[cfg(feature = "onnx")]
+struct Punctuator {
+    session: ort::session::Session,
+    vocab: WordPiece,
+    token_type_ids: bool,
+}
+
+#[cfg(feature = "onnx")]
+impl Punctuator {
+    fn new(config: &PunctuationConfig) -> Result<Self, NotlokError> {
+        let vocab = std::fs::read_to_string(&config.vocab_path)
+            .map_err(|e| NotlokError::ModelLoadFailed { message: format!("{}: {}", config.vocab_path, e) })?;
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let session = ort::session::Session::builder()
+            .and_then(|builder| builder.with_intra_threads(threads))
+            .and_then(|builder| builder.commit_from_file(&config.model_path))
+            .map_err(|e| NotlokError::ModelLoadFailed { message: e.to_string() })?;
+        let token_type_ids = session.inputs.iter().any(|input| input.name == "token_type_ids");
+        Ok(Self {
+            session,
+            vocab: WordPiece::new(&vocab),
+            token_type_ids,
+        })
+    }
+
+    /// One (punctuation label, capitalized) pair per word
+    fn label(&mut self, words: &[String]) -> Result<Vec<(usize, bool)>, NotlokError> {
+        let mut labels = Vec::with_capacity(words.len());
+        let mut window: Vec<Vec<i64>> = Vec::new(); // tokens of each word
+        for word in words {
+            let tokens = self.vocab.tokenize(word);
+            if !window.is_empty() && window.iter().map(Vec::len).sum::<usize>() + tokens.len() > MAX_WINDOW_TOKENS - 2 {
+                labels.extend(self.label_window(&window)?);
+                window.clear();
+            }
+            window.push(tokens);
+        }
+        if !window.is_empty() {
+            labels.extend(self.label_window(&window)?);
+        }
+        Ok(labels)
+    }
+
+    fn label_window(&mut self, words: &[Vec<i64>]) -> Result<Vec<(usize, bool)>, NotlokError> {
+        let mut ids = vec![self.vocab.cls];
+        let mut first_tokens = Vec::with_capacity(words.len());
+        for tokens in words {
+            first_tokens.push(ids.len());
+            ids.extend(tokens.iter().take(MAX_WINDOW_TOKENS - 2));
+        }
+        ids.push(self.vocab.sep);
+
+        let token_type_ids = self.token_type_ids;
+        let run = |session: &mut ort::session::Session| -> ort::Result<Vec<(usize, bool)>> {
+            let n = ids.len();
+            let mut inputs = ort::inputs![
+                "input_ids" => ort::value::Tensor::from_array(([1, n], ids.clone()))?,
+                "attention_mask" => ort::value::Tensor::from_array(([1, n], vec![1i64; n]))?,
+            ];
+            if token_type_ids {
+                inputs.push(("token_type_ids".into(), ort::value::Tensor::from_array(([1, n], vec![0i64; n]))?.into()));
+            }
+            let outputs = session.run(inputs)?;
+            let (punctuation_shape, punctuation) = outputs[0].try_extract_tensor::<f32>()?;
+            let (capitalization_shape, capitalization) = outputs[1].try_extract_tensor::<f32>()?;
+            let punctuation_labels = punctuation_shape.last().map_or(1, |&n| n.max(1) as usize);
+            let capitalization_labels = capitalization_shape.last().map_or(1, |&n| n.max(1) as usize);
+
+            Ok(first_tokens.iter()
+                .map(|&t| {
+                    let punctuation = punctuation.chunks(punctuation_labels).nth(t).map_or(0, argmax);
+                    let capitalized = capitalization.chunks(capitalization_labels).nth(t).is_some_and(|l| argmax(l) == 1);
+                    (punctuation, capitalized)
+                })
+                .collect())
+        };
+        run(&mut self.session).map_err(|e| NotlokError::TranscriptionFailed {
+            message: format!("Punctuation failed: {}", e),
+        })
+    }
+}
+
+#[cfg(not(feature = "onnx"))]
+struct Punctuator;
+
+#[cfg(not(feature = "onnx"))]
+impl Punctuator {
+    fn new(_config: &PunctuationConfig) -> Result<Self, NotlokError> {
+        Err(NotlokError::ModelLoadFailed {
+            message: "Punctuation restoration requires ONNX Runtime, which is not enabled in this build".to_string(),
+        })
+    }
+
+    fn label(&mut self, words: &[String]) -> Result<Vec<(usize, bool)>, NotlokError> {
+        Ok(vec![(0, false); words.len()])
+    }
+}
+
+/// Punctuates and capitalizes every segment. The whole transcript is labelled as one text,
+/// since a sentence often runs over a segment boundary.
+pub fn restore(segments: &mut [TranscriptSegment], config: &PunctuationConfig) -> Result<(), NotlokError> {
+    if !config.enabled || segments.is_empty() {
+        return Ok(());
+    }
+    let mut punctuator = Punctuator::new(config)?;
+
+    let words: Vec<Vec<String>> = segments.iter()
+        .map(|s| s.text.split_whitespace().map(str::to_lowercase).collect())
+        .collect();
+    let flat: Vec<String> = words.iter().flatten().cloned().collect();
+    let labels = punctuator.label(&flat)?;
+
+    let mut labels = labels.into_iter();
+    for (segment, words) in segments.iter_mut().zip(words) {
+        let restored: Vec<String> = words.into_iter()
+            .zip(labels.by_ref())
+            .map(|(word, (punctuation, capitalized))| {
+                let mut word = if capitalized { capitalize(&word) } else { word };
+                word.push_str(config.punctuation_labels.get(punctuation).map_or("", String::as_str));
+                word
+            })
+            .collect();
+        // Word timestamps stay usable when the words line up one to one
+        if segment.words.len() == restored.len() {
+            for (word, text) in segment.words.iter_mut().zip(&restored) {
+                word.text = text.clone();
+            }
+        }
+        segment.text = format!(" {}", restored.join(" "));
+    }
+    Ok(())
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(feature = "onnx")]
+fn argmax(values: &[f32]) -> usize {
+    values.iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map_or(0, |(i, _)| i)
+}
+
+#[cfg(feature = "onnx")]
+struct WordPiece {
+    ids: std::collections::HashMap<String, i64>,
+    unk: i64,
+    cls: i64,
+    sep: i64,
+}
+
+#[cfg(feature = "onnx")]
+impl WordPiece {
+    fn new(vocab: &str) -> Self {
+        let ids: std::collections::HashMap<String, i64> = vocab.lines()
+            .enumerate()
+            .map(|(i, token)| (token.trim_end().to_string(), i as i64))
+            .collect();
+        let special = |token: &str| ids.get(token).copied().unwrap_or(0);
+        let (unk, cls, sep) = (special("[UNK]"), special("[CLS]"), special("[SEP]"));
+        Self { ids, unk, cls, sep }
+    }
+
+    // Greedy longest match, pieces after the first are looked up with a "##" prefix
+    fn tokenize(&self, word: &str) -> Vec<i64> {
+        let chars: Vec<char> = word.chars().collect();
+        let mut tokens = Vec::new();
+        let mut start = 0;
+        while start < chars.len() {
+            let piece = (start + 1..=chars.len()).rev().find_map(|end| {
+                let text: String = chars[start..end].iter().collect();
+                let key = if start == 0 { text } else { format!("##{}", text) };
+                self.ids.get(&key).map(|&id| (id, end))
+            });
+            match piece {
+                Some((id, end)) => {
+                    tokens.push(id);
+                    start = end;
+                }
+                // A word that can't be split is unknown as a whole
+                None => return vec![self.unk],
+            }
+        }
+        tokens
+    }
+}
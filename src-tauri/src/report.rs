@@ -0,0 +1,416 @@
+// Structured meeting reports
+// The model is asked for one JSON object: a summary, decisions, action items with owner and
+// due date, open questions and topics with the time they came up. Its answer is checked
+// here before anything is stored, and sent back once for repair when it doesn't hold up.
+// The transcript goes out with [mm:ss] marks and speakers so times and owners can be given.
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::ai::{AiClient, TokenUsage};
+use crate::error::NotlokError;
+use crate::jobs::CancelToken;
+use crate::summarize::{self, render, SummarizerConfig};
+use crate::transcription::Transcript;
+
+const SCHEMA_PROMPT: &str = "Answer with a single JSON object and nothing else, in this form:
+{
+  \"summary\": \"a short paragraph on what the meeting was about and its outcome\",
+  \"decisions\": [\"each decision that was made\"],
+  \"action_items\": [{\"task\": \"what has to be done\", \"owner\": \"who does it, or null\", \"due_date\": \"YYYY-MM-DD, or null\"}],
+  \"open_questions\": [\"each question left unanswered\"],
+  \"topics\": [{\"title\": \"a topic that was discussed\", \"start\": \"mm:ss where it begins\"}]
+}
+The meeting took place on {date}, resolve relative due dates like \"next Friday\" from it. \
+Use the [mm:ss] marks of the transcript for the topics, in the order they came up. \
+Use empty lists for what the meeting didn't have.";
+
+const REPAIR_PROMPT: &str = "This answer doesn't follow the requested format: {error}
+
+{instructions}
+
+--- ANSWER ---
+{answer}
+--- END ANSWER ---
+
+Write the corrected JSON object only.";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MeetingReport {
+    pub summary: String,
+    pub decisions: Vec<String>,
+    pub action_items: Vec<ActionItem>,
+    pub open_questions: Vec<String>,
+    pub topics: Vec<Topic>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ActionItem {
+    pub task: String,
+    pub owner: Option<String>,
+    pub due_date: Option<String>, // YYYY-MM-DD
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Topic {
+    pub title: String,
+    pub start: f64, // seconds into the recording
+}
+
+// What the model is asked for, before validation
+#[derive(Deserialize)]
+struct RawReport {
+    summary: String,
+    #[serde(default)]
+    decisions: Vec<String>,
+    #[serde(default)]
+    action_items: Vec<RawActionItem>,
+    #[serde(default)]
+    open_questions: Vec<String>,
+    #[serde(default)]
+    topics: Vec<RawTopic>,
+}
+
+#[derive(Deserialize)]
+struct RawActionItem {
+    task: String,
+    owner: Option<String>,
+    due_date: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawTopic {
+    title: String,
+    start: String,
+}
+
+/// Extracts the report of `transcript`, recorded on `date` (YYYY-MM-DD), with `speakers`
+/// named as the user renamed them. `instructions` say what language to write in. Long
+/// transcripts go through the same map-reduce as text reports.
+#[allow(clippy::too_many_arguments)]
+pub async fn extract(
+    client: &AiClient,
+    config: &SummarizerConfig,
+    instructions: &str,
+    transcript: &Transcript,
+    speakers: &HashMap<usize, String>,
+    date: &str,
+    cancel: &CancelToken,
+    on_progress: impl FnMut(usize, usize),
+) -> Result<(MeetingReport, TokenUsage), NotlokError> {
+    let instructions = format!("{}\n\n{}", instructions, render(SCHEMA_PROMPT, &[("date", date)]));
+    let duration = transcript.segments.last().map_or(0.0, |s| s.end);
+    let completion = summarize::summarize(client, config, &instructions, &timestamped(transcript, speakers), cancel, on_progress, |_| {}).await?;
+    let mut usage = completion.usage;
+
+    let error = match validate(&completion.text, duration) {
+        Ok(report) => return Ok((report, usage)),
+        Err(error) => error,
+    };
+    let prompt = render(REPAIR_PROMPT, &[("error", &error), ("instructions", &instructions), ("answer", &completion.text)]);
+    let repaired = client.complete(&prompt, cancel, &mut |_| {}).await?;
+    usage.add(repaired.usage);
+    let report = validate(&repaired.text, duration).map_err(|message| NotlokError::InvalidReport { message })?;
+    Ok((report, usage))
+}
+
+/// One line per segment: `[mm:ss] Speaker 1: text`, or the name the speaker was given
+fn timestamped(transcript: &Transcript, speakers: &HashMap<usize, String>) -> String {
+    let lines: Vec<String> = transcript.segments.iter()
+        .filter(|s| !s.text.trim().is_empty())
+        .map(|s| match s.speaker {
+            Some(speaker) => format!("[{}] {}: {}", format_time(s.start), speaker_name(speaker, speakers), s.text.trim()),
+            None => format!("[{}] {}", format_time(s.start), s.text.trim()),
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// The transcript as one paragraph per turn, `Name: text`, for text reports. Without
+/// diarization it is just the text.
+pub fn labelled(transcript: &Transcript, speakers: &HashMap<usize, String>) -> String {
+    if transcript.segments.iter().all(|s| s.speaker.is_none()) {
+        return transcript.text.clone();
+    }
+    let mut turns: Vec<(Option<usize>, String)> = Vec::new();
+    for segment in transcript.segments.iter().filter(|s| !s.text.trim().is_empty()) {
+        match turns.last_mut() {
+            Some((speaker, text)) if *speaker == segment.speaker => {
+                text.push(' ');
+                text.push_str(segment.text.trim());
+            }
+            _ => turns.push((segment.speaker, segment.text.trim().to_string())),
+        }
+    }
+    let turns: Vec<String> = turns.into_iter()
+        .map(|(speaker, text)| match speaker {
+            Some(speaker) => format!("{}: {}", speaker_name(speaker, speakers), text),
+            None => text,
+        })
+        .collect();
+    turns.join("\n\n")
+}
+
+fn speaker_name(speaker: usize, speakers: &HashMap<usize, String>) -> String {
+    match speakers.get(&speaker) {
+        Some(name) => name.clone(),
+        None => format!("Speaker {}", speaker + 1),
+    }
+}
+
+fn format_time(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+// mm:ss or h:mm:ss
+fn parse_time(text: &str) -> Option<f64> {
+    let parts: Vec<u64> = text.trim().split(':').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+    let seconds = match parts[..] {
+        [m, s] if s < 60 => m * 60 + s,
+        [h, m, s] if m < 60 && s < 60 => h * 3600 + m * 60 + s,
+        _ => return None,
+    };
+    Some(seconds as f64)
+}
+
+/// Parses and checks the model's answer. The error says what is wrong, for the repair request.
+fn validate(answer: &str, duration: f64) -> Result<MeetingReport, String> {
+    // Models like to wrap JSON in code fences or a sentence
+    let json = match (answer.find('{'), answer.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &answer[start..=end],
+        _ => return Err("no JSON object found".to_string()),
+    };
+    let raw: RawReport = serde_json::from_str(json).map_err(|e| e.to_string())?;
+
+    let summary = raw.summary.trim().to_string();
+    if summary.is_empty() {
+        return Err("\"summary\" is empty".to_string());
+    }
+    let texts = |items: Vec<String>| -> Vec<String> {
+        items.into_iter().map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect()
+    };
+    // "null", "none" and "-" are how models tend to say nobody or no date
+    let optional = |value: Option<String>| {
+        value.map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty() && !matches!(v.to_lowercase().as_str(), "null" | "none" | "n/a" | "-"))
+    };
+
+    let mut action_items = Vec::with_capacity(raw.action_items.len());
+    for item in raw.action_items {
+        let task = item.task.trim().to_string();
+        if task.is_empty() {
+            continue;
+        }
+        let due_date = optional(item.due_date);
+        if let Some(date) = due_date.as_deref().filter(|d| !is_valid_date(d)) {
+            return Err(format!("due_date \"{}\" of \"{}\" is not a YYYY-MM-DD date", date, task));
+        }
+        action_items.push(ActionItem { task, owner: optional(item.owner), due_date });
+    }
+
+    let mut topics = Vec::with_capacity(raw.topics.len());
+    for topic in raw.topics {
+        let title = topic.title.trim().to_string();
+        if title.is_empty() {
+            continue;
+        }
+        let start = parse_time(&topic.start)
+            .ok_or_else(|| format!("start \"{}\" of topic \"{}\" is not a mm:ss time", topic.start, title))?;
+        // A little slack, the last segment may end before the model's rounded time
+        if start > duration + 60.0 {
+            return Err(format!("topic \"{}\" starts at {}, after the recording ends", title, topic.start));
+        }
+        topics.push(Topic { title, start });
+    }
+    topics.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    Ok(MeetingReport {
+        summary,
+        decisions: texts(raw.decisions),
+        action_items,
+        open_questions: texts(raw.open_questions),
+        topics,
+    })
+}
+
+fn is_valid_date(date: &str) -> bool {
+    let parts: Vec<&str> = date.split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return false;
+    };
+    // parse() alone would take "+1" for a month
+    let digits = |part: &str, len: usize| part.len() == len && part.bytes().all(|b| b.is_ascii_digit());
+    if !digits(year, 4) || !digits(month, 2) || !digits(day, 2) {
+        return false;
+    }
+    match (year.parse::<u32>(), month.parse::<u32>(), day.parse::<u32>()) {
+        (Ok(year), Ok(month @ 1..=12), Ok(day)) => day >= 1 && day <= days_in_month(year, month),
+        _ => false,
+    }
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// YYYY-MM-DD of a unix ms time, in UTC
+pub fn format_date(millis: u64) -> String {
+    // Days to civil date, from Howard Hinnant's date algorithms
+    let days = (millis / 86_400_000) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcription::TranscriptSegment;
+
+    fn segment(start: f64, speaker: Option<usize>, text: &str) -> TranscriptSegment {
+        TranscriptSegment { start, end: start + 2.0, text: text.to_string(), speaker, words: Vec::new(), avg_logprob: None }
+    }
+
+    fn diarized() -> Transcript {
+        Transcript::from_segments(vec![
+            segment(0.0, Some(0), " Başlayalım."),
+            segment(2.0, Some(0), " Bütçe hazır mı?"),
+            segment(65.0, Some(1), " Cuma günü biter."),
+            segment(68.0, Some(1), "  "),
+            segment(70.0, Some(0), " Tamam."),
+        ])
+    }
+
+    #[test]
+    fn transcript_uses_speaker_names() {
+        let speakers = HashMap::from([(1, "Ayşe".to_string())]);
+        assert_eq!(
+            timestamped(&diarized(), &speakers),
+            "[00:00] Speaker 1: Başlayalım.\n[00:02] Speaker 1: Bütçe hazır mı?\n[01:05] Ayşe: Cuma günü biter.\n[01:10] Speaker 1: Tamam."
+        );
+        assert_eq!(
+            labelled(&diarized(), &speakers),
+            "Speaker 1: Başlayalım. Bütçe hazır mı?\n\nAyşe: Cuma günü biter.\n\nSpeaker 1: Tamam."
+        );
+    }
+
+    #[test]
+    fn undiarized_transcript_is_left_alone() {
+        let mut transcript = Transcript::from_segments(vec![segment(0.0, None, " Merhaba.")]);
+        transcript.text = "Merhaba.".to_string();
+        assert_eq!(labelled(&transcript, &HashMap::new()), "Merhaba.");
+        assert_eq!(timestamped(&transcript, &HashMap::new()), "[00:00] Merhaba.");
+    }
+
+    const REPORT: &str = r#"{
+        "summary": "Bütçe konuşuldu.",
+        "decisions": ["Bütçe onaylandı", "  "],
+        "action_items": [
+            {"task": "Teklifi gönder", "owner": "Ayşe", "due_date": "2024-02-29"},
+            {"task": "Salonu ayarla", "owner": "null", "due_date": "N/A"},
+            {"task": "  ", "owner": null, "due_date": null}
+        ],
+        "open_questions": [],
+        "topics": [{"title": "Kapanış", "start": "01:05"}, {"title": "Bütçe", "start": "0:02"}]
+    }"#;
+
+    #[test]
+    fn reads_json_wrapped_in_fences_or_prose() {
+        for answer in [
+            REPORT.to_string(),
+            format!("```json\n{}\n```", REPORT),
+            format!("Here is the report:\n{}\nLet me know if anything is missing.", REPORT),
+        ] {
+            let report = validate(&answer, 70.0).unwrap();
+            assert_eq!(report.summary, "Bütçe konuşuldu.");
+            assert_eq!(report.decisions, vec!["Bütçe onaylandı"]);
+            assert_eq!(report.action_items.len(), 2);
+            // Sorted by time
+            let topics: Vec<(&str, f64)> = report.topics.iter().map(|t| (t.title.as_str(), t.start)).collect();
+            assert_eq!(topics, vec![("Bütçe", 2.0), ("Kapanış", 65.0)]);
+        }
+        assert_eq!(validate("I couldn't find a meeting in this transcript.", 70.0).unwrap_err(), "no JSON object found");
+        assert!(validate("} {", 70.0).is_err());
+        assert!(validate(r#"{"summary": "  "}"#, 70.0).unwrap_err().contains("summary"));
+    }
+
+    #[test]
+    fn placeholder_owners_and_dates_mean_none() {
+        let report = validate(REPORT, 70.0).unwrap();
+        let item = &report.action_items[1];
+        assert_eq!(item.task, "Salonu ayarla");
+        assert_eq!(item.owner, None);
+        assert_eq!(item.due_date, None);
+        for placeholder in ["null", "NULL", "None", "n/a", "N/A", "-", " "] {
+            let answer = format!(r#"{{"summary": "s", "action_items": [{{"task": "t", "owner": "{0}", "due_date": "{0}"}}]}}"#, placeholder);
+            let item = &validate(&answer, 0.0).unwrap().action_items[0];
+            assert_eq!((item.owner.as_deref(), item.due_date.as_deref()), (None, None), "{}", placeholder);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_due_dates() {
+        for date in ["2023-02-29", "2024-13-01", "2024-00-10", "2024-04-31", "2024-01-00", "2024-1-05", "2024-01-5", "24-01-05", "2024-+1-05", "2024/01/05", "next Friday"] {
+            let answer = format!(r#"{{"summary": "s", "action_items": [{{"task": "t", "due_date": "{}"}}]}}"#, date);
+            let error = validate(&answer, 0.0).unwrap_err();
+            assert!(error.contains(date), "{}: {}", date, error);
+        }
+    }
+
+    #[test]
+    fn february_29_only_in_leap_years() {
+        assert!(is_valid_date("2024-02-29"));
+        assert!(is_valid_date("2000-02-29")); // divisible by 400
+        assert!(!is_valid_date("1900-02-29")); // century
+        assert!(!is_valid_date("2100-02-29"));
+        assert!(!is_valid_date("2023-02-29"));
+        assert!(is_valid_date("2023-02-28"));
+        assert!(is_valid_date("2024-12-31"));
+    }
+
+    #[test]
+    fn rejects_topics_after_the_recording() {
+        let answer = r#"{"summary": "s", "topics": [{"title": "Son", "start": "12:00"}]}"#;
+        assert!(validate(answer, 660.0).is_ok()); // within the minute of slack
+        assert!(validate(answer, 600.0).unwrap_err().contains("after the recording ends"));
+        let answer = r#"{"summary": "s", "topics": [{"title": "Son", "start": "1:75"}]}"#;
+        assert!(validate(answer, 600.0).unwrap_err().contains("not a mm:ss time"));
+    }
+
+    #[test]
+    fn parses_times() {
+        assert_eq!(parse_time("01:05"), Some(65.0));
+        assert_eq!(parse_time(" 1:02:03 "), Some(3723.0));
+        assert_eq!(parse_time("1:60"), None);
+        assert_eq!(parse_time("65"), None);
+        assert_eq!(parse_time("a:05"), None);
+        assert_eq!(format_time(3723.9), "1:02:03");
+        assert_eq!(format_time(-1.0), "00:00");
+    }
+
+    #[test]
+    fn formats_dates() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(86_399_999), "1970-01-01");
+        assert_eq!(format_date(86_400_000), "1970-01-02");
+        assert_eq!(format_date(951_782_400_000), "2000-02-29");
+        assert_eq!(format_date(1_708_041_600_000), "2024-02-16");
+        assert_eq!(format_date(1_709_164_800_000), "2024-02-29");
+        assert_eq!(format_date(1_792_368_000_000), "2026-10-19");
+    }
+}
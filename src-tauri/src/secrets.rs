@@ -0,0 +1,243 @@
+// Secrets (API keys, the license key)
+// Kept in the platform credential store: the Keychain on macOS, Credential Manager on
+// Windows and the Secret Service on Linux. Linux sessions without a Secret Service (bare
+// window managers, some minimal distros) fall back to a file in the app data directory.
+// That file is obfuscation, not protection: it is encrypted, but with a key stored right
+// next to it, so anyone who can read one can read the other. Both are kept at 0600 so
+// only the user can, which is all the fallback really guarantees.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use crate::error::NotlokError;
+
+const SERVICE: &str = "app.notlok";
+const MAX_NAME_LEN: usize = 64;
+const NONCE_LEN: usize = 12;
+
+fn store_error(e: impl std::fmt::Display) -> NotlokError {
+    NotlokError::SecretStore { message: e.to_string() }
+}
+
+/// Secret names are short ids like "gemini-api-key"
+pub fn validate_name(name: &str) -> Result<(), NotlokError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(NotlokError::InvalidConfig { field: "name".to_string() })
+    }
+}
+
+pub struct Secrets {
+    fallback: FileStore,
+}
+
+impl Secrets {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            fallback: FileStore {
+                path: dir.join("secrets.enc"),
+                key_path: dir.join("secrets.key"),
+                lock: Mutex::new(()),
+            },
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<Option<String>, NotlokError> {
+        match keyring::Entry::new(SERVICE, name).and_then(|entry| entry.get_password()) {
+            Ok(value) => Ok(Some(value)),
+            // May have been saved to the file while the keyring was down
+            Err(keyring::Error::NoEntry) => self.fallback.get(name),
+            Err(e) if use_fallback(&e) => self.fallback.get(name),
+            Err(e) => Err(store_error(e)),
+        }
+    }
+
+    pub fn set(&self, name: &str, value: &str) -> Result<(), NotlokError> {
+        match keyring::Entry::new(SERVICE, name).and_then(|entry| entry.set_password(value)) {
+            // A copy left from a fallback save would come back after the keyring one is deleted
+            Ok(()) => self.fallback.delete(name),
+            Err(e) if use_fallback(&e) => self.fallback.set(name, value),
+            Err(e) => Err(store_error(e)),
+        }
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), NotlokError> {
+        match keyring::Entry::new(SERVICE, name).and_then(|entry| entry.delete_credential()) {
+            Ok(()) | Err(keyring::Error::NoEntry) => self.fallback.delete(name),
+            Err(e) if use_fallback(&e) => self.fallback.delete(name),
+            Err(e) => Err(store_error(e)),
+        }
+    }
+}
+
+// Only Linux has a keyring that may simply not be running
+fn use_fallback(e: &keyring::Error) -> bool {
+    cfg!(target_os = "linux") && matches!(e, keyring::Error::NoStorageAccess(_) | keyring::Error::PlatformFailure(_))
+}
+
+/// Every secret in one file: a random nonce followed by the ChaCha20-Poly1305 encrypted
+/// JSON map, rewritten whole on every change
+struct FileStore {
+    path: PathBuf,
+    key_path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileStore {
+    fn get(&self, name: &str) -> Result<Option<String>, NotlokError> {
+        let _guard = self.lock.lock()?;
+        Ok(self.read()?.remove(name))
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<(), NotlokError> {
+        let _guard = self.lock.lock()?;
+        let mut secrets = self.read()?;
+        secrets.insert(name.to_string(), value.to_string());
+        self.write(&secrets)
+    }
+
+    fn delete(&self, name: &str) -> Result<(), NotlokError> {
+        let _guard = self.lock.lock()?;
+        let mut secrets = self.read()?;
+        if secrets.remove(name).is_some() {
+            self.write(&secrets)?;
+        }
+        Ok(())
+    }
+
+    fn read(&self) -> Result<HashMap<String, String>, NotlokError> {
+        let data = match std::fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(e) => return Err(e.into()),
+        };
+        if data.len() < NONCE_LEN {
+            return Err(store_error("Secret file is truncated"));
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let plaintext = self.cipher()?
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| store_error("Secret file can't be decrypted"))?;
+        serde_json::from_slice(&plaintext).map_err(store_error)
+    }
+
+    fn write(&self, secrets: &HashMap<String, String>) -> Result<(), NotlokError> {
+        let plaintext = serde_json::to_vec(secrets).map_err(store_error)?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self.cipher()?.encrypt(&nonce, plaintext.as_ref()).map_err(store_error)?;
+
+        let mut data = nonce.to_vec();
+        data.extend_from_slice(&ciphertext);
+        let temp_path = self.path.with_extension("enc.tmp");
+        write_private(&temp_path, &data)?;
+        std::fs::rename(temp_path, &self.path)?;
+        Ok(())
+    }
+
+    // The key is created with the first secret
+    fn cipher(&self) -> Result<ChaCha20Poly1305, NotlokError> {
+        match std::fs::read(&self.key_path) {
+            Ok(key) if key.len() == 32 => {
+                // Files from before permissions were enforced on every write
+                restrict_permissions(&self.key_path)?;
+                Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+            }
+            Ok(_) => Err(store_error("Secret key file is invalid")),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+                write_private(&self.key_path, &key)?;
+                Ok(ChaCha20Poly1305::new(&key))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+// Owner read/write only where the platform has permissions for it
+fn write_private(path: &Path, data: &[u8]) -> Result<(), NotlokError> {
+    use std::io::Write;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    // The mode above only applies when the file is created
+    restrict_permissions(path)?;
+    file.write_all(data)?;
+    Ok(())
+}
+
+fn restrict_permissions(path: &Path) -> Result<(), NotlokError> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn mode(path: &Path) -> u32 {
+        std::fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[test]
+    fn file_store_round_trips_with_private_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore {
+            path: dir.path().join("secrets.enc"),
+            key_path: dir.path().join("secrets.key"),
+            lock: Mutex::new(()),
+        };
+        assert_eq!(store.get("gemini-api-key").unwrap(), None);
+
+        store.set("gemini-api-key", "AIza-test").unwrap();
+        store.set("license-key", "ABCD-1234").unwrap();
+        assert_eq!(store.get("gemini-api-key").unwrap().as_deref(), Some("AIza-test"));
+        assert_eq!(mode(&store.path), 0o600);
+        assert_eq!(mode(&store.key_path), 0o600);
+
+        store.delete("gemini-api-key").unwrap();
+        assert_eq!(store.get("gemini-api-key").unwrap(), None);
+        assert_eq!(store.get("license-key").unwrap().as_deref(), Some("ABCD-1234"));
+        // Not readable in plain text
+        let data = std::fs::read(&store.path).unwrap();
+        assert!(!data.windows(9).any(|w| w == b"ABCD-1234"));
+    }
+
+    #[test]
+    fn loose_permissions_are_tightened() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore {
+            path: dir.path().join("secrets.enc"),
+            key_path: dir.path().join("secrets.key"),
+            lock: Mutex::new(()),
+        };
+        store.set("license-key", "ABCD-1234").unwrap();
+        for path in [&store.path, &store.key_path] {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        }
+
+        store.set("license-key", "EFGH-5678").unwrap();
+        assert_eq!(mode(&store.path), 0o600);
+        assert_eq!(mode(&store.key_path), 0o600);
+    }
+}
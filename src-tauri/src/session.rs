@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PauseMarker {
+    pub paused_at: u64, // unix ms
+    pub resumed_at: Option<u64>,
+    pub audio_offset: f64, // seconds of recorded audio before the pause
+    pub auto: bool,        // paused by the silence monitor rather than the user
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct DeviceSwitch {
+    pub at: u64, // unix ms
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct SessionMetadata {
+    pub id: String,
+    pub started_at: u64, // unix ms
+    pub ended_at: Option<u64>,
+    pub pauses: Vec<PauseMarker>,
+    pub device_switches: Vec<DeviceSwitch>, // input device changes forced by hot-plug
+}
+
+/// Timing and metadata of the recording in progress
+pub struct RecordingSession {
+    pub metadata: SessionMetadata,
+    started: Instant,
+    paused_since: Option<Instant>,
+    paused_total: Duration,
+}
+
+impl RecordingSession {
+    pub fn new() -> Self {
+        let started_at = now_millis();
+        Self {
+            metadata: SessionMetadata {
+                id: started_at.to_string(),
+                started_at,
+                ended_at: None,
+                pauses: Vec::new(),
+                device_switches: Vec::new(),
+            },
+            started: Instant::now(),
+            paused_since: None,
+            paused_total: Duration::ZERO,
+        }
+    }
+
+    pub fn pause(&mut self, audio_offset: f64, auto: bool) {
+        if self.paused_since.is_some() {
+            return;
+        }
+        self.paused_since = Some(Instant::now());
+        self.metadata.pauses.push(PauseMarker {
+            paused_at: now_millis(),
+            resumed_at: None,
+            audio_offset,
+            auto,
+        });
+    }
+
+    pub fn resume(&mut self) {
+        if let Some(since) = self.paused_since.take() {
+            self.paused_total += since.elapsed();
+            if let Some(marker) = self.metadata.pauses.last_mut() {
+                marker.resumed_at = Some(now_millis());
+            }
+        }
+    }
+
+    #[cfg_attr(not(any(target_os = "macos", target_os = "windows")), allow(dead_code))]
+    pub fn switch_device(&mut self, from: &str, to: &str) {
+        self.metadata.device_switches.push(DeviceSwitch {
+            at: now_millis(),
+            from: from.to_string(),
+            to: to.to_string(),
+        });
+    }
+
+    pub fn finish(&mut self) {
+        // A pause that lasts until the end keeps resumed_at empty
+        if let Some(since) = self.paused_since.take() {
+            self.paused_total += since.elapsed();
+        }
+        self.metadata.ended_at = Some(now_millis());
+    }
+
+    /// Wall-clock time spent recording, excluding pauses
+    pub fn active_duration(&self) -> Duration {
+        let paused = self.paused_total + self.paused_since.map(|s| s.elapsed()).unwrap_or_default();
+        self.started.elapsed().saturating_sub(paused)
+    }
+}
@@ -0,0 +1,69 @@
+// Settings persisted by the backend, kept as JSON in the app data directory.
+// Loaded once at startup; every setter writes the whole file back.
+
+use std::collections::HashMap;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::ai::AiConfig;
+use crate::audio_capture::{AutoPauseConfig, InputConfig};
+use crate::diarization::DiarizationConfig;
+use crate::error::NotlokError;
+use crate::hallucination::HallucinationFilter;
+use crate::punctuation::PunctuationConfig;
+use crate::summarize::SummarizerConfig;
+use crate::transcription::TranscriptionOptions;
+use crate::vad::VadConfig;
+use crate::vocabulary::Replacement;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub transcription_options: HashMap<String, TranscriptionOptions>, // by model id
+    pub vocabulary: Vec<String>,
+    pub replacements: Vec<Replacement>, // applied in order after transcription
+    pub hallucination_filter: HallucinationFilter,
+    pub punctuation: PunctuationConfig, // for engines without punctuation of their own
+    pub summarizer: SummarizerConfig,
+    pub ai: AiConfig, // providers reports can be generated with
+    pub vad: VadConfig,
+    pub diarization: DiarizationConfig,
+    pub auto_pause: AutoPauseConfig,
+    pub input: InputConfig,
+}
+
+impl Settings {
+    /// A missing file gives the defaults; a corrupt one is logged and replaced on the next save
+    pub fn load(path: &Path) -> Self {
+        let data = match std::fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                eprintln!("Failed to read settings: {}", e);
+                return Self::default();
+            }
+        };
+        serde_json::from_str(&data).unwrap_or_else(|e| {
+            eprintln!("Failed to parse settings: {}", e);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), NotlokError> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let data = serde_json::to_string_pretty(self).map_err(|e| NotlokError::internal(e.to_string()))?;
+        // Written next to the target and renamed so a crash can't leave a half-written file
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, data)?;
+        std::fs::rename(temp_path, path)?;
+        Ok(())
+    }
+
+    pub fn transcription_options(&self, model_id: Option<&str>) -> TranscriptionOptions {
+        model_id
+            .and_then(|id| self.transcription_options.get(id))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
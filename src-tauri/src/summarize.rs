@@ -0,0 +1,401 @@
+// Meeting reports
+// A transcript too long for one request is summarized part by part (map) and the notes of
+// the parts are combined into the report (reduce). Only the final request is streamed back
+// token by token. Which model answers is up to the `AiClient`.
+
+use serde::{Deserialize, Serialize};
+use crate::ai::{AiClient, Completion, TokenUsage};
+use crate::error::NotlokError;
+use crate::jobs::CancelToken;
+
+/// What the report is about, each has its own instructions in `i18n`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReportTemplate {
+    MeetingNotes,
+    #[default]
+    MeetingSummary,
+    ActionItems,
+    DecisionLog,
+    Custom, // instructions written by the user
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SummarizerConfig {
+    pub temperature: f32,
+    pub max_chunk_chars: usize, // transcript text per request, keep well inside the model's context
+    pub prompt: String,         // transcript that fits one request: {instructions}, {transcript}
+    pub map_prompt: String,     // one part of a long one: {instructions}, {part}, {parts}, {transcript}
+    pub reduce_prompt: String,  // notes of the parts: {instructions}, {notes}
+}
+
+impl Default for SummarizerConfig {
+    fn default() -> Self {
+        Self {
+            temperature: 0.3,
+            max_chunk_chars: 12_000,
+            prompt: "{instructions}\n\n--- TRANSCRIPT ---\n{transcript}\n--- END TRANSCRIPT ---".to_string(),
+            map_prompt: "This is part {part} of {parts} of a meeting transcript. Write concise notes on \
+                everything in it that matters for the instructions below, keeping names, numbers, \
+                [mm:ss] times, decisions and action items. Write in the transcript's language.\n\n\
+                Instructions: {instructions}\n\n--- TRANSCRIPT PART ---\n{transcript}\n--- END TRANSCRIPT PART ---"
+                .to_string(),
+            reduce_prompt: "These are notes on consecutive parts of one meeting transcript. Use them as \
+                if they were the transcript.\n\n{instructions}\n\n--- NOTES ---\n{notes}\n--- END NOTES ---"
+                .to_string(),
+        }
+    }
+}
+
+/// Fills `{name}` placeholders in one pass, so values that contain braces stay as they are
+pub fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let value = rest.find('}').and_then(|close| {
+            let name = &rest[1..close];
+            values.iter().find(|(n, _)| *n == name).map(|(_, v)| (*v, close))
+        });
+        match value {
+            Some((value, close)) => {
+                out.push_str(value);
+                rest = &rest[close + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Summarizes `transcript` following `instructions`. `on_progress` gets the finished and
+/// total request count, `on_token` the text of the final answer as it streams in. The usage
+/// returned covers every request made.
+pub async fn summarize(
+    client: &AiClient,
+    config: &SummarizerConfig,
+    instructions: &str,
+    transcript: &str,
+    cancel: &CancelToken,
+    mut on_progress: impl FnMut(usize, usize),
+    mut on_token: impl FnMut(&str),
+) -> Result<Completion, NotlokError> {
+    let parts = split_text(transcript, config.max_chunk_chars);
+    if parts.len() <= 1 {
+        on_progress(0, 1);
+        let prompt = render(&config.prompt, &[("instructions", instructions), ("transcript", transcript)]);
+        let summary = client.complete(&prompt, cancel, &mut on_token).await?;
+        on_progress(1, 1);
+        return Ok(summary);
+    }
+
+    // Map: notes for every part
+    let total = parts.len() + 1;
+    let count = parts.len().to_string();
+    let mut usage = TokenUsage::default();
+    let mut notes = Vec::with_capacity(parts.len());
+    for (i, part) in parts.iter().enumerate() {
+        on_progress(i, total);
+        let number = (i + 1).to_string();
+        let prompt = render(&config.map_prompt, &[
+            ("instructions", instructions),
+            ("part", &number),
+            ("parts", &count),
+            ("transcript", part),
+        ]);
+        let completion = client.complete(&prompt, cancel, &mut |_| {}).await?;
+        usage.add(completion.usage);
+        notes.push(completion.text);
+    }
+
+    // Reduce: notes that still don't fit are combined in groups first
+    loop {
+        let groups = group_notes(&notes, config.max_chunk_chars);
+        if groups.len() <= 1 {
+            break;
+        }
+        let mut combined = Vec::with_capacity(groups.len());
+        for group in groups {
+            let prompt = render(&config.reduce_prompt, &[("instructions", instructions), ("notes", &group)]);
+            let completion = client.complete(&prompt, cancel, &mut |_| {}).await?;
+            usage.add(completion.usage);
+            combined.push(completion.text);
+        }
+        notes = combined;
+    }
+    on_progress(parts.len(), total);
+    let prompt = render(&config.reduce_prompt, &[("instructions", instructions), ("notes", &notes.join("\n\n"))]);
+    let mut summary = client.complete(&prompt, cancel, &mut on_token).await?;
+    usage.add(summary.usage);
+    summary.usage = usage;
+    on_progress(total, total);
+    Ok(summary)
+}
+
+// Packs notes into groups that fit one request. A group always takes at least two notes,
+// so every round makes the list shorter even when single notes are over the limit.
+fn group_notes(notes: &[String], max_chars: usize) -> Vec<String> {
+    let mut groups: Vec<Vec<&str>> = Vec::new();
+    let mut size = 0;
+    for note in notes {
+        let len = note.chars().count();
+        match groups.last_mut() {
+            Some(group) if group.len() < 2 || size + len <= max_chars => {
+                group.push(note);
+                size += len;
+            }
+            _ => {
+                groups.push(vec![note]);
+                size = len;
+            }
+        }
+    }
+    groups.into_iter().map(|group| group.join("\n\n")).collect()
+}
+
+/// Splits text into parts of at most `max_chars`, preferring line ends, then sentence
+/// ends, then spaces
+pub fn split_text(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+
+    for piece in pieces(text, max_chars) {
+        let len = piece.chars().count();
+        if current_len + len > max_chars && !current.trim().is_empty() {
+            parts.push(current.trim().to_string());
+            current.clear();
+            current_len = 0;
+        }
+        current.push_str(piece);
+        current_len += len;
+    }
+    if !current.trim().is_empty() {
+        parts.push(current.trim().to_string());
+    }
+    parts
+}
+
+// Sentences (with their trailing whitespace), cut further at spaces or anywhere when one
+// alone is longer than `max_chars`
+fn pieces(text: &str, max_chars: usize) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let ends_sentence = c == '\n' || (matches!(c, '.' | '?' | '!') && chars.peek().is_some_and(|(_, n)| n.is_whitespace()));
+        if ends_sentence {
+            // Keep the whitespace after it with the sentence
+            let mut end = i + c.len_utf8();
+            while let Some(&(j, n)) = chars.peek().filter(|(_, n)| n.is_whitespace()) {
+                end = j + n.len_utf8();
+                chars.next();
+            }
+            sentences.push(&text[start..end]);
+            start = end;
+        }
+    }
+    if start < text.len() {
+        sentences.push(&text[start..]);
+    }
+
+    sentences.into_iter().flat_map(|sentence| split_long(sentence, max_chars)).collect()
+}
+
+fn split_long(text: &str, max_chars: usize) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut rest = text;
+    while rest.chars().count() > max_chars {
+        let limit = rest.char_indices().nth(max_chars).map_or(rest.len(), |(i, _)| i);
+        let cut = rest[..limit].rfind(' ').filter(|&i| i > 0).map_or(limit, |i| i + 1);
+        out.push(&rest[..cut]);
+        rest = &rest[cut..];
+    }
+    out.push(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+    use crate::ai::{self, AiConfig, ProviderKind};
+
+    fn client(server: &MockServer) -> AiClient {
+        let mut config = AiConfig::default();
+        config.local.base_url = format!("{}/v1", server.uri());
+        AiClient::new(ai::provider(ProviderKind::Local, &config, None).unwrap(), 0, 0.3).unwrap()
+    }
+
+    fn prompt(request: &Request) -> String {
+        let body: Value = serde_json::from_slice(&request.body).unwrap();
+        body["messages"][0]["content"].as_str().unwrap().to_string()
+    }
+
+    fn answer(text: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{ "message": { "content": text } }],
+            "usage": { "prompt_tokens": 10, "completion_tokens": 2 },
+        }))
+    }
+
+    async fn requests(server: &MockServer) -> Vec<String> {
+        server.received_requests().await.unwrap().iter().map(prompt).collect()
+    }
+
+    #[tokio::test]
+    async fn short_transcript_is_one_request() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).and(path("/v1/chat/completions")).respond_with(answer("Summary")).mount(&server).await;
+
+        let mut progress = Vec::new();
+        let mut tokens = Vec::new();
+        let config = SummarizerConfig::default();
+        let completion = summarize(&client(&server), &config, "Summarize.", "Hello there.", &CancelToken::default(),
+            |done, total| progress.push((done, total)), |t| tokens.push(t.to_string())).await.unwrap();
+
+        assert_eq!(completion.text, "Summary");
+        assert_eq!(completion.usage, TokenUsage { prompt_tokens: 10, completion_tokens: 2, estimated: false });
+        assert_eq!(progress, vec![(0, 1), (1, 1)]);
+        assert_eq!(tokens, vec!["Summary"]);
+        assert_eq!(requests(&server).await, vec!["Summarize.\n\n--- TRANSCRIPT ---\nHello there.\n--- END TRANSCRIPT ---"]);
+    }
+
+    #[tokio::test]
+    async fn long_transcript_is_mapped_then_reduced() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(|request: &Request| {
+                let prompt = prompt(request);
+                match prompt.strip_prefix("Part ") {
+                    Some(rest) => answer(&format!("notes {}", &rest[..1])),
+                    None => answer("Report"),
+                }
+            })
+            .mount(&server)
+            .await;
+
+        let config = SummarizerConfig {
+            max_chunk_chars: 35,
+            map_prompt: "Part {part}/{parts}: {transcript}".to_string(),
+            reduce_prompt: "{instructions} {notes}".to_string(),
+            ..SummarizerConfig::default()
+        };
+        let transcript = "First we talked about the budget. Then about hiring. Last came the launch date.";
+        let mut progress = Vec::new();
+        let mut tokens = Vec::new();
+        let completion = summarize(&client(&server), &config, "Summarize.", transcript, &CancelToken::default(),
+            |done, total| progress.push((done, total)), |t| tokens.push(t.to_string())).await.unwrap();
+
+        assert_eq!(requests(&server).await, vec![
+            "Part 1/3: First we talked about the budget.",
+            "Part 2/3: Then about hiring.",
+            "Part 3/3: Last came the launch date.",
+            "Summarize. notes 1\n\nnotes 2\n\nnotes 3",
+        ]);
+        assert_eq!(progress, vec![(0, 4), (1, 4), (2, 4), (3, 4), (4, 4)]);
+        // Only the final answer is streamed to the caller, usage covers all four requests
+        assert_eq!(tokens, vec!["Report"]);
+        assert_eq!(completion.text, "Report");
+        assert_eq!(completion.usage, TokenUsage { prompt_tokens: 40, completion_tokens: 8, estimated: false });
+    }
+
+    #[tokio::test]
+    async fn final_answer_streams_token_by_token() {
+        let server = MockServer::start().await;
+        let events = [
+            r#"data: {"choices":[{"delta":{"role":"assistant"}}]}"#,
+            r#"data: {"choices":[{"delta":{"content":"Toplantı "}}]}"#,
+            r#"data: {"choices":[{"delta":{"content":"özeti"}}]}"#,
+            "data: [DONE]",
+        ];
+        let body = events.map(|e| format!("{}\n\n", e)).concat();
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let mut tokens = Vec::new();
+        let completion = summarize(&client(&server), &SummarizerConfig::default(), "Summarize.", "Hello there.",
+            &CancelToken::default(), |_, _| {}, |t| tokens.push(t.to_string())).await.unwrap();
+
+        assert_eq!(tokens, vec!["Toplantı ", "özeti"]);
+        assert_eq!(completion.text, "Toplantı özeti");
+        // The local server sent no counts
+        assert!(completion.usage.estimated);
+        assert_eq!(completion.usage.completion_tokens, ai::estimate_tokens("Toplantı özeti"));
+    }
+
+    #[tokio::test]
+    async fn cancelling_stops_the_map() {
+        let server = MockServer::start().await;
+        let cancel = CancelToken::default();
+        let token = cancel.clone();
+        Mock::given(method("POST"))
+            .respond_with(move |_: &Request| {
+                token.cancel();
+                answer("notes")
+            })
+            .mount(&server)
+            .await;
+
+        let config = SummarizerConfig { max_chunk_chars: 35, ..SummarizerConfig::default() };
+        let transcript = "First we talked about the budget. Then about hiring. Last came the launch date.";
+        let mut progress = Vec::new();
+        let result = summarize(&client(&server), &config, "Summarize.", transcript, &cancel,
+            |done, total| progress.push((done, total)), |_| {}).await;
+
+        assert!(matches!(result, Err(NotlokError::Cancelled)));
+        assert_eq!(requests(&server).await.len(), 1);
+        assert_eq!(progress, vec![(0, 4), (1, 4)]);
+    }
+
+    #[test]
+    fn splits_at_sentence_ends() {
+        assert_eq!(split_text("One. Two. Three.", 10), vec!["One. Two.", "Three."]);
+        assert_eq!(split_text("Bir.\nİki?  Üç!", 5), vec!["Bir.", "İki?", "Üç!"]);
+        assert_eq!(split_text("  ", 10), Vec::<String>::new());
+    }
+
+    #[test]
+    fn splits_multibyte_text_by_characters() {
+        let text = "Çarşamba günü ölçüm sonuçlarını değerlendirdik. Şirket içi eğitim ertelendi.";
+        let parts = split_text(text, 50);
+        assert_eq!(parts, vec!["Çarşamba günü ölçüm sonuçlarını değerlendirdik.", "Şirket içi eğitim ertelendi."]);
+        // 48 characters but far more bytes, still one part
+        assert_eq!(split_text(&parts[0], 48), vec![parts[0].clone()]);
+    }
+
+    #[test]
+    fn cuts_long_sentences_at_spaces_then_anywhere() {
+        let parts = split_text("ve sonra da şöyle oldu ki", 10);
+        assert_eq!(parts, vec!["ve sonra", "da şöyle", "oldu ki"]);
+        assert!(parts.iter().all(|p| p.chars().count() <= 10));
+
+        assert_eq!(split_text("ğğğğğğğğğğ", 4), vec!["ğğğğ", "ğğğğ", "ğğ"]);
+    }
+
+    #[test]
+    fn render_leaves_braces_in_values_alone() {
+        let out = render("{instructions}\n{transcript}", &[
+            ("instructions", "Answer as {\"summary\": ...}"),
+            ("transcript", "He said {transcript} and {instructions}"),
+        ]);
+        assert_eq!(out, "Answer as {\"summary\": ...}\nHe said {transcript} and {instructions}");
+    }
+
+    #[test]
+    fn render_keeps_unknown_placeholders() {
+        assert_eq!(render("{a} {b} {", &[("a", "1")]), "1 {b} {");
+        assert_eq!(render("{{a}}", &[("a", "1")]), "{1}");
+    }
+}
@@ -1,18 +1,177 @@
-use whisper_rs::{WhisperContext, FullParams, SamplingStrategy};
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, Ordering};
+use whisper_rs::{WhisperContext, WhisperError, WhisperState, FullParams, SamplingStrategy};
+use serde::{Serialize, Deserialize};
+use crate::chunking;
+use crate::error::NotlokError;
+use crate::hallucination::HallucinationFilter;
+use crate::jobs::CancelToken;
+use crate::vad::{SpeechRegion, SAMPLE_RATE};
 // use sherpa_rs::OnlineRecognizer; // Placeholder for actual import
 
+// whisper.cpp skips inputs shorter than 1s, so short speech regions are padded with silence
+const MIN_WHISPER_SAMPLES: usize = SAMPLE_RATE + SAMPLE_RATE / 10;
+
+// Text from the previous chunk added to the prompt of the next one
+const PROMPT_CONTEXT_CHARS: usize = 200;
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TranscriptSegment {
+    pub start: f64, // seconds from the start of the recording
+    pub end: f64,
+    pub text: String,
+    #[serde(default)]
+    pub speaker: Option<usize>, // set by diarization
+    #[serde(default)]
+    pub words: Vec<TranscriptWord>, // only filled when word timestamps are enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avg_logprob: Option<f32>, // mean log probability of the text tokens, None if the engine has none
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TranscriptWord {
+    pub start: f64, // seconds from the start of the recording
+    pub end: f64,
+    pub text: String,
+    pub confidence: f32, // mean probability of the word's tokens, 0..1
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct Transcript {
+    pub text: String,
+    pub segments: Vec<TranscriptSegment>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translation: Option<Box<Transcript>>, // English version, kept next to the original
+}
+
+impl Transcript {
+    pub fn from_segments(segments: Vec<TranscriptSegment>) -> Self {
+        let text = segments.iter()
+            .map(|s| s.text.trim())
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Self { text, segments, translation: None }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Task {
+    #[default]
+    Transcribe,
+    Translate, // to English, on top of the original-language transcript
+}
+
+/// Whisper decoding settings, stored per model
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TranscriptionOptions {
+    pub beam_size: u32, // 1 decodes greedily
+    pub temperature: f32,
+    pub temperature_increment: f32, // step for retrying failed decodes hotter, 0 disables the fallback
+    pub threads: Option<u32>,       // None leaves whisper's default
+    pub no_speech_threshold: f32,
+    pub initial_prompt: Option<String>,
+    pub suppress_blank: bool,
+    pub suppress_non_speech_tokens: bool,
+    pub task: Task, // used when a transcription doesn't ask for one
+    pub word_timestamps: bool,
+}
+
+impl Default for TranscriptionOptions {
+    fn default() -> Self {
+        Self {
+            beam_size: 1,
+            temperature: 0.0,
+            temperature_increment: 0.2,
+            threads: None,
+            no_speech_threshold: 0.6,
+            initial_prompt: None,
+            suppress_blank: true,
+            suppress_non_speech_tokens: false,
+            task: Task::Transcribe,
+            word_timestamps: false,
+        }
+    }
+}
+
 pub enum TranscriberModel {
     Whisper(WhisperTranscriber),
     Parakeet(ParakeetTranscriber),
 }
 
 impl TranscriberModel {
-    pub fn transcribe(&mut self, audio_data: &[f32]) -> Result<String, String> {
+    pub fn transcribe(
+        &mut self,
+        audio_data: &[f32],
+        options: &TranscriptionOptions,
+        cancel: &CancelToken,
+    ) -> Result<Vec<TranscriptSegment>, NotlokError> {
         match self {
-            TranscriberModel::Whisper(t) => t.transcribe(audio_data),
+            TranscriberModel::Whisper(t) => t.transcribe(audio_data, options, cancel),
             TranscriberModel::Parakeet(t) => t.transcribe(audio_data),
         }
     }
+
+    /// Transcribes only the given speech regions, keeping timestamps relative to the full buffer.
+    /// Long regions are decoded in overlapping chunks; `on_progress` gets the seconds of
+    /// speech done so far and the total after each one. Whisper output goes through the
+    /// hallucination filter chunk by chunk.
+    pub fn transcribe_regions(
+        &mut self,
+        audio_data: &[f32],
+        regions: &[SpeechRegion],
+        options: &TranscriptionOptions,
+        filter: &HallucinationFilter,
+        cancel: &CancelToken,
+        mut on_progress: impl FnMut(f64, f64),
+    ) -> Result<Transcript, NotlokError> {
+        let chunks = chunking::plan_chunks(audio_data, regions);
+        let total = chunks.iter().map(|c| c.keep.sample_count()).sum::<usize>() as f64 / SAMPLE_RATE as f64;
+        let mut done = 0.0;
+
+        let mut segments = Vec::new();
+        let mut chunk_options = options.clone();
+        for chunk in &chunks {
+            cancel.check()?;
+            let context = chunking::prompt_context(&segments, PROMPT_CONTEXT_CHARS);
+            // Whisper keeps the end of a long prompt, so the context goes first and is what
+            // gets cut, not the vocabulary
+            let prompt = format!("{} {}", context, options.initial_prompt.as_deref().unwrap_or_default());
+            chunk_options.initial_prompt = Some(prompt.trim().to_string()).filter(|p| !p.is_empty());
+
+            let offset = chunk.decode.start_secs();
+            let mut decoded = self.transcribe(&audio_data[chunk.decode.start..chunk.decode.end], &chunk_options, cancel)?;
+            if let TranscriberModel::Whisper(_) = self {
+                decoded = filter.apply(decoded);
+            }
+            for segment in &mut decoded {
+                segment.start += offset;
+                segment.end += offset;
+                for word in &mut segment.words {
+                    word.start += offset;
+                    word.end += offset;
+                }
+            }
+            chunking::merge(&mut segments, chunk, decoded);
+
+            done += chunk.keep.sample_count() as f64 / SAMPLE_RATE as f64;
+            on_progress(done, total);
+        }
+        Ok(Transcript::from_segments(segments))
+    }
+}
+
+fn whisper_error(context: &'static str) -> impl FnOnce(WhisperError) -> NotlokError {
+    move |e| NotlokError::TranscriptionFailed {
+        message: format!("{}: {}", context, e),
+    }
+}
+
+// whisper.cpp polls this during decoding, `flag` is the AtomicBool of a CancelToken
+unsafe extern "C" fn abort_requested(flag: *mut c_void) -> bool {
+    (*(flag as *const AtomicBool)).load(Ordering::Relaxed)
 }
 
 pub struct WhisperTranscriber {
@@ -20,32 +179,141 @@ pub struct WhisperTranscriber {
 }
 
 impl WhisperTranscriber {
-    pub fn new(model_path: &str) -> Result<Self, String> {
+    pub fn new(model_path: &str) -> Result<Self, NotlokError> {
         let ctx = WhisperContext::new_with_params(model_path, Default::default())
-            .map_err(|e| format!("Failed to load Whisper model: {}", e))?;
+            .map_err(|e| NotlokError::ModelLoadFailed { message: e.to_string() })?;
         Ok(Self { ctx })
     }
 
-    pub fn transcribe(&mut self, audio_data: &[f32]) -> Result<String, String> {
-        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
-        params.set_language(Some("tr")); 
+    pub fn transcribe(
+        &mut self,
+        audio_data: &[f32],
+        options: &TranscriptionOptions,
+        cancel: &CancelToken,
+    ) -> Result<Vec<TranscriptSegment>, NotlokError> {
+        let strategy = if options.beam_size > 1 {
+            SamplingStrategy::BeamSearch { beam_size: options.beam_size as i32, patience: -1.0 }
+        } else {
+            SamplingStrategy::Greedy { best_of: 1 }
+        };
+        let mut params = FullParams::new(strategy);
+        params.set_language(Some("tr"));
         params.set_print_special(false);
         params.set_print_progress(false);
         params.set_print_realtime(false);
         params.set_print_timestamps(false);
+        params.set_token_timestamps(options.word_timestamps);
+        params.set_translate(options.task == Task::Translate);
+        params.set_temperature(options.temperature);
+        params.set_temperature_inc(options.temperature_increment);
+        params.set_no_speech_thold(options.no_speech_threshold);
+        params.set_suppress_blank(options.suppress_blank);
+        params.set_suppress_non_speech_tokens(options.suppress_non_speech_tokens);
+        if let Some(threads) = options.threads {
+            params.set_n_threads(threads as i32);
+        }
+        if let Some(prompt) = options.initial_prompt.as_deref().filter(|p| !p.trim().is_empty()) {
+            params.set_initial_prompt(prompt);
+        }
+        // SAFETY: `cancel` outlives `state.full` below, the only call that uses the pointer
+        unsafe {
+            params.set_abort_callback(Some(abort_requested));
+            params.set_abort_callback_user_data(cancel.as_ptr() as *mut c_void);
+        }
 
-        let mut state = self.ctx.create_state().map_err(|e| format!("Failed to create state: {}", e))?;
-        state.full(params, audio_data).map_err(|e| format!("Failed to run model: {}", e))?;
+        let mut padded;
+        let audio_data = if audio_data.len() < MIN_WHISPER_SAMPLES {
+            padded = audio_data.to_vec();
+            padded.resize(MIN_WHISPER_SAMPLES, 0.0);
+            &padded[..]
+        } else {
+            audio_data
+        };
 
-        let num_segments = state.full_n_segments().map_err(|e| format!("Failed to get segments: {}", e))?;
-        let mut text = String::new();
+        let mut state = self.ctx.create_state().map_err(whisper_error("Failed to create state"))?;
+        let result = state.full(params, audio_data);
+        cancel.check()?;
+        result.map_err(whisper_error("Failed to run model"))?;
+
+        let num_segments = state.full_n_segments().map_err(whisper_error("Failed to get segments"))?;
+        let mut segments = Vec::with_capacity(num_segments as usize);
         for i in 0..num_segments {
-            let segment = state.full_get_segment_text(i).map_err(|e| format!("Failed to get segment text: {}", e))?;
-            text.push_str(&segment);
-            text.push(' ');
+            let text = state.full_get_segment_text(i).map_err(whisper_error("Failed to get segment text"))?;
+            // Segment timestamps are reported in centiseconds
+            let t0 = state.full_get_segment_t0(i).map_err(whisper_error("Failed to get segment start"))?;
+            let t1 = state.full_get_segment_t1(i).map_err(whisper_error("Failed to get segment end"))?;
+            let words = if options.word_timestamps {
+                self.segment_words(&state, i)?
+            } else {
+                Vec::new()
+            };
+            segments.push(TranscriptSegment {
+                start: t0 as f64 / 100.0,
+                end: t1 as f64 / 100.0,
+                text,
+                speaker: None,
+                words,
+                avg_logprob: self.segment_avg_logprob(&state, i)?,
+            });
         }
 
-        Ok(text)
+        Ok(segments)
+    }
+
+    fn segment_avg_logprob(&self, state: &WhisperState, segment: i32) -> Result<Option<f32>, NotlokError> {
+        let eot = self.ctx.token_eot();
+        let num_tokens = state.full_n_tokens(segment).map_err(whisper_error("Failed to get tokens"))?;
+
+        let (mut sum, mut count) = (0.0, 0);
+        for t in 0..num_tokens {
+            let data = state.full_get_token_data(segment, t).map_err(whisper_error("Failed to get token data"))?;
+            if data.id < eot {
+                sum += data.plog;
+                count += 1;
+            }
+        }
+        Ok((count > 0).then(|| sum / count as f32))
+    }
+
+    // Joins a segment's tokens into words. A token starting with a space begins a new word;
+    // text is collected as bytes since a multi-byte character can span two tokens.
+    fn segment_words(&self, state: &WhisperState, segment: i32) -> Result<Vec<TranscriptWord>, NotlokError> {
+        let eot = self.ctx.token_eot();
+        let num_tokens = state.full_n_tokens(segment).map_err(whisper_error("Failed to get tokens"))?;
+
+        let mut words: Vec<(Vec<u8>, TranscriptWord, u32)> = Vec::new(); // bytes, word, token count
+        for t in 0..num_tokens {
+            let data = state.full_get_token_data(segment, t).map_err(whisper_error("Failed to get token data"))?;
+            // Timestamp and other special tokens sort after end-of-text
+            if data.id >= eot {
+                continue;
+            }
+            let bytes = self.ctx.token_to_cstr(data.id).map_err(whisper_error("Failed to get token text"))?.to_bytes();
+            let start = data.t0 as f64 / 100.0;
+            let end = data.t1 as f64 / 100.0;
+            match words.last_mut() {
+                Some((text, word, count)) if !bytes.starts_with(b" ") => {
+                    text.extend_from_slice(bytes);
+                    word.end = end;
+                    word.confidence += data.p;
+                    *count += 1;
+                }
+                _ => words.push((
+                    bytes.to_vec(),
+                    TranscriptWord { start, end, text: String::new(), confidence: data.p },
+                    1,
+                )),
+            }
+        }
+
+        Ok(words.into_iter()
+            .map(|(bytes, mut word, count)| {
+                word.text = String::from_utf8_lossy(&bytes).trim().to_string();
+                word.confidence /= count as f32;
+                word
+            })
+            .filter(|word| !word.text.is_empty())
+            .collect())
     }
 }
 
@@ -55,20 +323,27 @@ pub struct ParakeetTranscriber {
 }
 
 impl ParakeetTranscriber {
-    pub fn new(model_path: &str) -> Result<Self, String> {
+    pub fn new(model_path: &str) -> Result<Self, NotlokError> {
         // Initialize Sherpa/Parakeet model here
         // let recognizer = OnlineRecognizer::new(model_path)...
-        Ok(Self { 
+        Ok(Self {
             model_path: model_path.to_string(),
-            // recognizer 
+            // recognizer
         })
     }
 
-    pub fn transcribe(&mut self, _audio_data: &[f32]) -> Result<String, String> {
+    pub fn transcribe(&mut self, audio_data: &[f32]) -> Result<Vec<TranscriptSegment>, NotlokError> {
         // Implement Parakeet transcription logic
         // self.recognizer.accept_waveform(audio_data)...
         // self.recognizer.get_result()...
-        
-        Ok(format!("Parakeet transcription placeholder for model: {}", self.model_path))
+
+        Ok(vec![TranscriptSegment {
+            start: 0.0,
+            end: audio_data.len() as f64 / SAMPLE_RATE as f64,
+            text: format!("Parakeet transcription placeholder for model: {}", self.model_path),
+            speaker: None,
+            words: Vec::new(),
+            avg_logprob: None,
+        }])
     }
 }
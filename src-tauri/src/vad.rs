@@ -0,0 +1,315 @@
+// Voice activity detection
+// Splits a 16kHz mono buffer into speech regions so silence is never sent to the transcriber
+
+use serde::{Deserialize, Serialize};
+use crate::error::NotlokError;
+
+pub const SAMPLE_RATE: usize = 16000;
+
+// Same threshold get_audio_stats uses to count silent samples
+pub const SILENCE_THRESHOLD: f32 = 0.01;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpeechRegion {
+    pub start: usize, // sample index (inclusive)
+    pub end: usize,   // sample index (exclusive)
+}
+
+impl SpeechRegion {
+    pub fn start_secs(&self) -> f64 {
+        self.start as f64 / SAMPLE_RATE as f64
+    }
+
+    pub fn sample_count(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum VadEngine {
+    Energy,
+    Silero { model_path: String },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VadConfig {
+    pub enabled: bool,
+    pub engine: VadEngine,
+    pub threshold: f32,      // RMS level (energy) or speech probability (model)
+    pub frame_ms: u32,
+    pub min_speech_ms: u32,  // shorter bursts are treated as noise
+    pub min_silence_ms: u32, // shorter pauses don't split a region
+    pub padding_ms: u32,     // kept on both sides so word edges aren't clipped
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            engine: VadEngine::Energy,
+            threshold: SILENCE_THRESHOLD,
+            frame_ms: 30,
+            min_speech_ms: 250,
+            min_silence_ms: 1000,
+            padding_ms: 200,
+        }
+    }
+}
+
+/// Scores a single frame, higher means more likely speech
+trait FrameClassifier {
+    fn score(&mut self, frame: &[f32]) -> Result<f32, NotlokError>;
+
+    /// Frame length the classifier needs, None takes the configured one
+    fn frame_len(&self) -> Option<usize> {
+        None
+    }
+}
+
+struct EnergyClassifier;
+
+impl FrameClassifier for EnergyClassifier {
+    fn score(&mut self, frame: &[f32]) -> Result<f32, NotlokError> {
+        Ok(rms(frame))
+    }
+}
+
+// Silero VAD v5: 512 new samples per call at 16kHz, preceded by the last 64 of the previous
+// call, with a recurrent state carried from one call to the next
+#[cfg(feature = "onnx")]
+const SILERO_FRAME: usize = 512;
+#[cfg(feature = "onnx")]
+const SILERO_CONTEXT: usize = 64;
+#[cfg(feature = "onnx")]
+const SILERO_STATE: usize = 2 * 128;
+
+#[cfg(feature = "onnx")]
+struct SileroClassifier {
+    session: ort::session::Session,
+    state: Vec<f32>,   // [2, 1, 128]
+    context: Vec<f32>, // tail of the previous frame
+}
+
+#[cfg(feature = "onnx")]
+impl SileroClassifier {
+    fn new(model_path: &str) -> Result<Self, NotlokError> {
+        let session = ort::session::Session::builder()
+            .and_then(|builder| builder.with_intra_threads(1))
+            .and_then(|builder| builder.commit_from_file(model_path))
+            .map_err(|e| NotlokError::ModelLoadFailed { message: e.to_string() })?;
+        Ok(Self {
+            session,
+            state: vec![0.0; SILERO_STATE],
+            context: vec![0.0; SILERO_CONTEXT],
+        })
+    }
+}
+
+#[cfg(feature = "onnx")]
+impl FrameClassifier for SileroClassifier {
+    fn score(&mut self, frame: &[f32]) -> Result<f32, NotlokError> {
+        let mut input = Vec::with_capacity(SILERO_CONTEXT + SILERO_FRAME);
+        input.extend_from_slice(&self.context);
+        input.extend_from_slice(frame);
+        input.resize(SILERO_CONTEXT + SILERO_FRAME, 0.0); // the last frame may be short
+        self.context.copy_from_slice(&input[input.len() - SILERO_CONTEXT..]);
+
+        let state = &mut self.state;
+        let run = |session: &mut ort::session::Session| -> ort::Result<f32> {
+            let outputs = session.run(ort::inputs![
+                "input" => ort::value::Tensor::from_array(([1, SILERO_CONTEXT + SILERO_FRAME], input))?,
+                "state" => ort::value::Tensor::from_array(([2, 1, 128], state.clone()))?,
+                "sr" => ort::value::Tensor::from_array(([1], vec![SAMPLE_RATE as i64]))?,
+            ])?;
+            let (_, probability) = outputs["output"].try_extract_tensor::<f32>()?;
+            let (_, next_state) = outputs["stateN"].try_extract_tensor::<f32>()?;
+            state.copy_from_slice(next_state);
+            Ok(probability.first().copied().unwrap_or(0.0))
+        };
+        run(&mut self.session).map_err(|e| NotlokError::TranscriptionFailed {
+            message: format!("Voice activity detection failed: {}", e),
+        })
+    }
+
+    fn frame_len(&self) -> Option<usize> {
+        Some(SILERO_FRAME)
+    }
+}
+
+#[cfg(not(feature = "onnx"))]
+struct SileroClassifier;
+
+#[cfg(not(feature = "onnx"))]
+impl SileroClassifier {
+    fn new(_model_path: &str) -> Result<Self, NotlokError> {
+        Err(NotlokError::ModelLoadFailed {
+            message: "Silero VAD requires ONNX Runtime, which is not enabled in this build".to_string(),
+        })
+    }
+}
+
+#[cfg(not(feature = "onnx"))]
+impl FrameClassifier for SileroClassifier {
+    fn score(&mut self, _frame: &[f32]) -> Result<f32, NotlokError> {
+        Ok(0.0)
+    }
+}
+
+pub fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum: f32 = samples.iter().map(|s| s * s).sum();
+    (sum / samples.len() as f32).sqrt()
+}
+
+fn ms_to_samples(ms: u32) -> usize {
+    SAMPLE_RATE * ms as usize / 1000
+}
+
+/// Returns the speech regions in `audio`, ordered and non-overlapping.
+/// When VAD is disabled the whole buffer is returned as a single region.
+pub fn detect_speech(audio: &[f32], config: &VadConfig) -> Result<Vec<SpeechRegion>, NotlokError> {
+    if audio.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !config.enabled {
+        return Ok(vec![SpeechRegion { start: 0, end: audio.len() }]);
+    }
+
+    let mut classifier: Box<dyn FrameClassifier> = match &config.engine {
+        VadEngine::Energy => Box::new(EnergyClassifier),
+        VadEngine::Silero { model_path } => Box::new(SileroClassifier::new(model_path)?),
+    };
+    let threshold = config.threshold;
+
+    let frame_len = classifier.frame_len().unwrap_or_else(|| ms_to_samples(config.frame_ms.max(10)));
+
+    // Raw per-frame decisions
+    let mut regions: Vec<SpeechRegion> = Vec::new();
+    let mut current: Option<usize> = None;
+    for (i, frame) in audio.chunks(frame_len).enumerate() {
+        let start = i * frame_len;
+        let is_speech = classifier.score(frame)? >= threshold;
+        match (is_speech, current) {
+            (true, None) => current = Some(start),
+            (false, Some(s)) => {
+                regions.push(SpeechRegion { start: s, end: start });
+                current = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = current {
+        regions.push(SpeechRegion { start: s, end: audio.len() });
+    }
+
+    // Bridge short pauses
+    let min_silence = ms_to_samples(config.min_silence_ms);
+    let mut merged: Vec<SpeechRegion> = Vec::with_capacity(regions.len());
+    for region in regions {
+        match merged.last_mut() {
+            Some(last) if region.start - last.end < min_silence => last.end = region.end,
+            _ => merged.push(region),
+        }
+    }
+
+    // Drop clicks and short noise bursts
+    let min_speech = ms_to_samples(config.min_speech_ms);
+    merged.retain(|r| r.sample_count() >= min_speech);
+
+    // Pad and merge whatever now overlaps
+    let padding = ms_to_samples(config.padding_ms);
+    let mut padded: Vec<SpeechRegion> = Vec::with_capacity(merged.len());
+    for region in merged {
+        let region = SpeechRegion {
+            start: region.start.saturating_sub(padding),
+            end: (region.end + padding).min(audio.len()),
+        };
+        match padded.last_mut() {
+            Some(last) if region.start <= last.end => last.end = region.end,
+            _ => padded.push(region),
+        }
+    }
+
+    Ok(padded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: usize = SAMPLE_RATE / 1000;
+
+    // Silence with a tone over each (start ms, end ms) span
+    fn audio(total_ms: usize, speech: &[(usize, usize)]) -> Vec<f32> {
+        let mut audio = vec![0.0; total_ms * MS];
+        for &(start, end) in speech {
+            for (i, sample) in audio[start * MS..end * MS].iter_mut().enumerate() {
+                *sample = 0.5 * (i as f32 * 0.1).sin();
+            }
+        }
+        audio
+    }
+
+    fn config(min_speech_ms: u32, min_silence_ms: u32, padding_ms: u32) -> VadConfig {
+        VadConfig {
+            frame_ms: 10,
+            min_speech_ms,
+            min_silence_ms,
+            padding_ms,
+            ..VadConfig::default()
+        }
+    }
+
+    fn region(start_ms: usize, end_ms: usize) -> SpeechRegion {
+        SpeechRegion { start: start_ms * MS, end: end_ms * MS }
+    }
+
+    #[test]
+    fn disabled_returns_the_whole_buffer() {
+        let config = VadConfig { enabled: false, ..VadConfig::default() };
+        assert_eq!(detect_speech(&audio(1000, &[]), &config).unwrap(), vec![region(0, 1000)]);
+        assert!(detect_speech(&[], &config).unwrap().is_empty());
+    }
+
+    #[test]
+    fn silence_has_no_regions() {
+        assert!(detect_speech(&audio(2000, &[]), &config(0, 0, 0)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn short_pauses_are_bridged() {
+        let audio = audio(3000, &[(500, 1000), (1300, 1800), (2500, 2800)]);
+        // 300ms pause merged, 700ms pause kept
+        let regions = detect_speech(&audio, &config(0, 500, 0)).unwrap();
+        assert_eq!(regions, vec![region(500, 1800), region(2500, 2800)]);
+    }
+
+    #[test]
+    fn bursts_under_min_speech_are_dropped() {
+        let audio = audio(3000, &[(500, 550), (1500, 2000)]);
+        let regions = detect_speech(&audio, &config(250, 100, 0)).unwrap();
+        assert_eq!(regions, vec![region(1500, 2000)]);
+    }
+
+    #[test]
+    fn padding_is_clamped_and_merges_overlaps() {
+        let audio = audio(3000, &[(100, 600), (1000, 1500), (2900, 3000)]);
+        let regions = detect_speech(&audio, &config(50, 100, 250)).unwrap();
+        // First two meet once padded, the last one is cut at the end of the buffer
+        assert_eq!(regions, vec![region(0, 1750), region(2650, 3000)]);
+    }
+
+    #[cfg(not(feature = "onnx"))]
+    #[test]
+    fn silero_without_onnx_is_an_error() {
+        let config = VadConfig {
+            engine: VadEngine::Silero { model_path: "silero_vad.onnx".to_string() },
+            ..VadConfig::default()
+        };
+        assert!(matches!(detect_speech(&audio(1000, &[]), &config), Err(NotlokError::ModelLoadFailed { .. })));
+    }
+}
@@ -0,0 +1,159 @@
+// Domain vocabulary
+// The term list is given to Whisper as part of the initial prompt so product names and
+// English terms in Turkish speech come out spelled right; whatever still goes wrong is
+// fixed afterwards by the find/replace dictionary.
+
+use serde::{Deserialize, Serialize};
+use crate::transcription::Transcript;
+
+// Whisper only reads the last ~224 prompt tokens, longer prompts lose the start silently
+const MAX_PROMPT_CHARS: usize = 800;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replacement {
+    pub find: String,
+    pub replace: String,
+    #[serde(default)]
+    pub case_sensitive: bool,
+}
+
+/// Joins the user's own prompt with the vocabulary, dropping terms that don't fit
+pub fn build_prompt(initial_prompt: Option<&str>, vocabulary: &[String]) -> Option<String> {
+    let mut prompt = initial_prompt.map(str::trim).unwrap_or_default().to_string();
+    for term in vocabulary {
+        let separator = if prompt.is_empty() { "" } else if prompt.ends_with(['.', ',']) { " " } else { ", " };
+        if prompt.chars().count() + separator.chars().count() + term.chars().count() > MAX_PROMPT_CHARS {
+            break;
+        }
+        prompt.push_str(separator);
+        prompt.push_str(term);
+    }
+    (!prompt.is_empty()).then_some(prompt)
+}
+
+/// Applies the dictionary to every segment, in order, and rebuilds the text
+pub fn apply_replacements(transcript: Transcript, replacements: &[Replacement]) -> Transcript {
+    if replacements.is_empty() {
+        return transcript;
+    }
+    let segments = transcript.segments.into_iter()
+        .map(|mut segment| {
+            for replacement in replacements {
+                segment.text = replace_words(&segment.text, replacement);
+            }
+            segment
+        })
+        .collect();
+    Transcript::from_segments(segments)
+}
+
+fn chars_match(a: char, b: char, case_sensitive: bool) -> bool {
+    a == b || (!case_sensitive && a.to_lowercase().eq(b.to_lowercase()))
+}
+
+// Only whole words are replaced, so "AI" doesn't rewrite the middle of "Aile"
+fn replace_words(text: &str, replacement: &Replacement) -> String {
+    let find: Vec<char> = replacement.find.chars().collect();
+    let (Some(&first), Some(&last)) = (find.first(), find.last()) else {
+        return text.to_string();
+    };
+    let chars: Vec<char> = text.chars().collect();
+
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let end = i + find.len();
+        let starts_word = !first.is_alphanumeric() || i == 0 || !chars[i - 1].is_alphanumeric();
+        let ends_word = end <= chars.len()
+            && (!last.is_alphanumeric() || end == chars.len() || !chars[end].is_alphanumeric());
+        if starts_word
+            && ends_word
+            && chars[i..end].iter().zip(&find).all(|(&a, &b)| chars_match(a, b, replacement.case_sensitive))
+        {
+            out.push_str(&replacement.replace);
+            i = end;
+        } else {
+            out.push(chars[i]);
+            i += 1;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcription::TranscriptSegment;
+
+    fn replacement(find: &str, replace: &str, case_sensitive: bool) -> Replacement {
+        Replacement { find: find.to_string(), replace: replace.to_string(), case_sensitive }
+    }
+
+    fn terms(terms: &[&str]) -> Vec<String> {
+        terms.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn joins_prompt_and_terms() {
+        assert_eq!(build_prompt(None, &[]), None);
+        assert_eq!(build_prompt(Some("  "), &[]), None);
+        assert_eq!(build_prompt(None, &terms(&["Kubernetes", "Notlok"])).as_deref(), Some("Kubernetes, Notlok"));
+        assert_eq!(build_prompt(Some(" Haftalık toplantı. "), &terms(&["Jira"])).as_deref(), Some("Haftalık toplantı. Jira"));
+        assert_eq!(build_prompt(Some("Sprint planı"), &terms(&["Jira"])).as_deref(), Some("Sprint planı, Jira"));
+    }
+
+    #[test]
+    fn prompt_stops_at_the_limit() {
+        // 9 chars and 2 for the separator per term after the first
+        let vocabulary = vec!["çğışöüÇĞİ".to_string(); 100];
+        let prompt = build_prompt(None, &vocabulary).unwrap();
+        assert_eq!(prompt.chars().count(), 9 + 71 * 11);
+        assert!(prompt.chars().count() <= MAX_PROMPT_CHARS);
+
+        // Exactly at the limit still fits, one more char doesn't
+        let exact = "a".repeat(MAX_PROMPT_CHARS - 3);
+        assert_eq!(build_prompt(Some(&exact), &terms(&["b"])).unwrap().chars().count(), MAX_PROMPT_CHARS);
+        assert_eq!(build_prompt(Some(&exact), &terms(&["bc"])).as_deref(), Some(exact.as_str()));
+        // Terms after one that doesn't fit are dropped too, the list is in priority order
+        assert_eq!(build_prompt(Some(&exact), &terms(&["bc", "d"])).as_deref(), Some(exact.as_str()));
+    }
+
+    #[test]
+    fn replaces_whole_words_only() {
+        let ai = replacement("AI", "yapay zeka", false);
+        assert_eq!(replace_words("AI ve Aile", &ai), "yapay zeka ve Aile");
+        assert_eq!(replace_words("(ai), OpenAI, AIs", &ai), "(yapay zeka), OpenAI, AIs");
+        assert_eq!(replace_words("Türkçe AI'ı", &ai), "Türkçe yapay zeka'ı");
+        // Non-word edges match anywhere
+        assert_eq!(replace_words("v1.2+", &replacement(".2+", " 2 plus", false)), "v1 2 plus");
+        assert_eq!(replace_words("metin", &replacement("", "x", false)), "metin");
+    }
+
+    #[test]
+    fn follows_case_sensitivity() {
+        assert_eq!(replace_words("chatgpt ve ChatGpt", &replacement("ChatGPT", "ChatGPT", false)), "ChatGPT ve ChatGPT");
+        let sensitive = replacement("Go", "Golang", true);
+        assert_eq!(replace_words("Go ile go", &sensitive), "Golang ile go");
+        assert_eq!(replace_words("ŞUBAT ayı", &replacement("şubat", "Şubat", false)), "Şubat ayı");
+    }
+
+    #[test]
+    fn applies_replacements_in_order() {
+        let segment = |text: &str| TranscriptSegment {
+            start: 0.0,
+            end: 1.0,
+            text: text.to_string(),
+            speaker: None,
+            words: Vec::new(),
+            avg_logprob: None,
+        };
+        let transcript = Transcript::from_segments(vec![segment(" kubernetis kurulumu"), segment(" k8s hazır")]);
+        let replacements = [replacement("kubernetis", "k8s", false), replacement("k8s", "Kubernetes", true)];
+        let transcript = apply_replacements(transcript, &replacements);
+        assert_eq!(transcript.segments[0].text, " Kubernetes kurulumu");
+        assert_eq!(transcript.text, "Kubernetes kurulumu Kubernetes hazır");
+        // The other way round the first rule has nothing left to match
+        let transcript = apply_replacements(Transcript::from_segments(vec![segment("kubernetis")]), &[replacements[1].clone(), replacements[0].clone()]);
+        assert_eq!(transcript.text, "k8s");
+    }
+}
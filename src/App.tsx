@@ -1,4 +1,4 @@
-import { useState, useEffect } from "react";
+import { useState, useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { getVersion } from "@tauri-apps/api/app";
@@ -28,9 +28,24 @@ const translations = {
     turkish: "Türkçe",
     english: "English",
     auto: "Otomatik",
+    transcriptionTask: "Çıktı",
+    taskTranscribe: "Orijinal dilde yaz",
+    taskTranslate: "İngilizceye çevir",
+    translation: "İngilizce Çeviri",
+    transcribing: "Yazıya dökülüyor",
+    translating: "Çevriliyor",
+    cancelTranscription: "İptal Et",
     activeModel: "Aktif Model",
     startRecording: "Kayda Başla",
     stopRecording: "Kaydı Durdur",
+    pauseRecording: "Duraklat",
+    resumeRecording: "Devam Et",
+    recordingPaused: "Kayıt duraklatıldı",
+    captureErrorMicrophone: "Mikrofon kaydı hatası",
+    captureErrorSystem: "Sistem sesi kaydı hatası",
+    systemAudio: "Sistem sesi",
+    sourceNoSamples: "hiç ses gelmedi",
+    sourceDiagnostics: "{source}: {seconds} sn ses geldi, en yüksek seviye {peak}",
     loadModelFirst: "Önce Model Yükleyin",
     status: "Durum",
     transcript: "Transkript",
@@ -92,11 +107,29 @@ const translations = {
     aiProvider: "AI Sağlayıcı",
     notlokAI: "Notlok AI",
     geminiAI: "Gemini AI",
-    geminiApiKey: "Gemini API Key",
-    enterGeminiKey: "Gemini API anahtarınızı girin",
+    openaiAI: "OpenAI",
+    anthropicAI: "Anthropic",
+    apiKey: "API Anahtarı",
+    enterApiKey: "API anahtarınızı girin",
+    apiKeySaved: "Kayıtlı, değiştirmek için yenisini girin",
+    removeApiKey: "Anahtarı Sil",
+    localAI: "Yerel Model (Ollama / llama.cpp)",
+    serverUrl: "Sunucu Adresi",
+    modelName: "Model",
+    localModelPlaceholder: "Sunucunun varsayılanı",
+    stopGenerating: "Durdur",
     customPrompt: "Özel Prompt",
     defaultPrompt: "Bu toplantı transkriptini analiz et ve önemli noktaları, kararları ve aksiyonları özetle:",
     reportResult: "Rapor Sonucu",
+    extractMeetingReport: "Toplantı Raporu Çıkar",
+    meetingReportTitle: "Toplantı Raporu",
+    reportSummary: "Özet",
+    decisions: "Kararlar",
+    openQuestions: "Açık Sorular",
+    topics: "Konular",
+    owner: "Sorumlu",
+    dueDate: "Son Tarih",
+    allActionItems: "Tüm Aksiyon Maddeleri",
     noTranscript: "Rapor oluşturmak için önce kayıt yapın",
     reportError: "Rapor oluşturulurken hata oluştu",
     estimatedTime: "Tahmini süre: 5-6 dakika",
@@ -110,14 +143,12 @@ const translations = {
     meetingSummary: "Toplantı Özeti",
     actionItems: "Aksiyon Maddeleri",
     decisionLog: "Karar Günlüğü",
-    meetingNotesPrompt: "Bu toplantı transkriptinden detaylı toplantı notları oluştur. Katılımcıları, tartışılan konuları, önemli noktaları ve sonuçları madde madde listele. Türkçe yaz.",
-    meetingSummaryPrompt: "Bu toplantı transkriptini analiz et ve kısa bir yönetici özeti hazırla. Ana konuları, alınan kararları ve sonraki adımları 2-3 paragrafta özetle. Türkçe yaz.",
-    actionItemsPrompt: "Bu toplantı transkriptinden tüm aksiyon maddelerini çıkar. Her madde için sorumlu kişiyi (varsa), görevi ve son tarihi (varsa) belirt. Öncelik sırasına göre listele. Türkçe yaz.",
-    decisionLogPrompt: "Bu toplantı transkriptinden alınan tüm kararları tespit et. Her karar için bağlamı, kararın kendisini, gerekçesini ve etkisini açıkla. Türkçe yaz.",
     noHistory: "Henüz kayıt yok",
     deleteRecord: "Sil",
     viewRecord: "Görüntüle",
     backToList: "Listeye Dön",
+    speaker: "Konuşmacı",
+    renameSpeaker: "İsim ver",
     recordDate: "Tarih",
     recordDuration: "Süre",
     clearHistory: "Geçmişi Temizle",
@@ -152,6 +183,54 @@ const translations = {
     premiumOnly: "Sadece Premium",
     removeLicense: "Lisansı Kaldır",
     confirmRemoveLicense: "Lisansı kaldırmak istediğinizden emin misiniz?",
+    qualityTiers: {
+      fast: "Hızlı, düşük kalite",
+      balanced: "Dengeli",
+      good: "İyi kalite",
+      very_good: "Çok iyi kalite",
+      best: "En iyi kalite",
+    },
+    englishOnly: "Yalnızca İngilizce",
+    downloaded: "İndirildi",
+    errors: {
+      ModelNotFound: "Model bulunamadı: {model_id}",
+      ModelNotDownloaded: "Model indirilmemiş: {model_id}",
+      UnsupportedModel: "Bilinmeyen model türü: {model_id}",
+      ModelLoadFailed: "Model yüklenemedi: {message}",
+      NoModelLoaded: "Model yüklenmemiş, transkript yok.",
+      DownloadFailed: "İndirme başarısız: {message}",
+      TranscriptionFailed: "Transkripsiyon başarısız: {message}",
+      Cancelled: "Transkripsiyon iptal edildi",
+      JobNotFound: "Transkripsiyon bulunamadı",
+      Busy: "Sırada çok fazla transkripsiyon var, lütfen bekleyin",
+      LlmUnavailable: "Yerel model sunucusuna ulaşılamadı: {url}",
+      AiRequestFailed: "{provider} isteği başarısız: {message}",
+      RateLimited: "{provider} istek sınırına ulaşıldı, biraz sonra tekrar deneyin",
+      MissingApiKey: "{provider} için API anahtarı girilmemiş",
+      SessionNotFound: "Kayıt bulunamadı, önce transkript oluşturun",
+      InvalidReport: "Model geçerli bir rapor döndürmedi: {message}",
+      SecretStore: "Anahtar deposu hatası: {message}",
+      LicenseInvalid: "Geçersiz lisans anahtarı",
+      LicenseEmailMismatch: "E-posta adresi lisans sahibiyle eşleşmiyor",
+      ActivationLimitReached: "Aktivasyon limiti aşıldı. Lütfen lisanslarınızı deaktif edip tekrar deneyin.",
+      LicenseServer: "Lisans aktifleştirme başarısız: {message}",
+      NotlokAiFailed: "API Hatası ({status}): {message}",
+      AlreadyRecording: "Kayıt zaten devam ediyor",
+      NotRecording: "Kayıt yapılmıyor",
+      AlreadyPaused: "Kayıt zaten duraklatıldı",
+      NotPaused: "Kayıt duraklatılmadı",
+      PermissionDenied: "İzin verilmedi: {permission}",
+      DeviceUnavailable: "Ses cihazı kullanılamıyor: {device}",
+      AudioStream: "Ses akışı hatası: {message}",
+      CaptureTimeout: "Ses kaydı zamanında başlatılamadı",
+      CaptureFailed: "Ses kaydı başarısız: {errors}",
+      NoAudioCaptured: "Hiç ses kaydedilmedi ({sources})",
+      PlatformNotSupported: "Bu platform desteklenmiyor",
+      InvalidLanguage: "Geçersiz dil: {language}. tr, en veya auto kullanın",
+      InvalidConfig: "Geçersiz ayar: {field}",
+      Io: "Dosya hatası: {message}",
+      Internal: "Beklenmeyen hata: {message}",
+    },
   },
   en: {
     title: "Notlok",
@@ -174,9 +253,24 @@ const translations = {
     turkish: "Turkish",
     english: "English",
     auto: "Auto",
+    transcriptionTask: "Output",
+    taskTranscribe: "Original language",
+    taskTranslate: "Translate to English",
+    translation: "English Translation",
+    transcribing: "Transcribing",
+    translating: "Translating",
+    cancelTranscription: "Cancel",
     activeModel: "Active Model",
     startRecording: "Start Recording",
     stopRecording: "Stop Recording",
+    pauseRecording: "Pause",
+    resumeRecording: "Resume",
+    recordingPaused: "Recording paused",
+    captureErrorMicrophone: "Microphone capture error",
+    captureErrorSystem: "System audio capture error",
+    systemAudio: "System audio",
+    sourceNoSamples: "no audio received",
+    sourceDiagnostics: "{source}: received {seconds}s of audio, peak level {peak}",
     loadModelFirst: "Load Model First",
     status: "Status",
     transcript: "Transcript",
@@ -238,11 +332,29 @@ const translations = {
     aiProvider: "AI Provider",
     notlokAI: "Notlok AI",
     geminiAI: "Gemini AI",
-    geminiApiKey: "Gemini API Key",
-    enterGeminiKey: "Enter your Gemini API key",
+    openaiAI: "OpenAI",
+    anthropicAI: "Anthropic",
+    apiKey: "API Key",
+    enterApiKey: "Enter your API key",
+    apiKeySaved: "Saved, enter a new one to replace it",
+    removeApiKey: "Remove Key",
+    localAI: "Local Model (Ollama / llama.cpp)",
+    serverUrl: "Server URL",
+    modelName: "Model",
+    localModelPlaceholder: "Server default",
+    stopGenerating: "Stop",
     customPrompt: "Custom Prompt",
     defaultPrompt: "Analyze this meeting transcript and summarize key points, decisions, and action items:",
     reportResult: "Report Result",
+    extractMeetingReport: "Extract Meeting Report",
+    meetingReportTitle: "Meeting Report",
+    reportSummary: "Summary",
+    decisions: "Decisions",
+    openQuestions: "Open Questions",
+    topics: "Topics",
+    owner: "Owner",
+    dueDate: "Due Date",
+    allActionItems: "All Action Items",
     noTranscript: "Record a meeting first to generate a report",
     reportError: "Error generating report",
     estimatedTime: "Estimated time: 5-6 minutes",
@@ -256,14 +368,12 @@ const translations = {
     meetingSummary: "Meeting Summary",
     actionItems: "Action Items",
     decisionLog: "Decision Log",
-    meetingNotesPrompt: "Create detailed meeting notes from this transcript. List participants, topics discussed, key points, and outcomes in bullet points. Write in English.",
-    meetingSummaryPrompt: "Analyze this meeting transcript and prepare a brief executive summary. Summarize main topics, decisions made, and next steps in 2-3 paragraphs. Write in English.",
-    actionItemsPrompt: "Extract all action items from this meeting transcript. For each item, specify the responsible person (if mentioned), the task, and deadline (if mentioned). List by priority. Write in English.",
-    decisionLogPrompt: "Identify all decisions made in this meeting transcript. For each decision, explain the context, the decision itself, rationale, and impact. Write in English.",
     noHistory: "No recordings yet",
     deleteRecord: "Delete",
     viewRecord: "View",
     backToList: "Back to List",
+    speaker: "Speaker",
+    renameSpeaker: "Rename",
     recordDate: "Date",
     recordDuration: "Duration",
     clearHistory: "Clear History",
@@ -298,20 +408,184 @@ const translations = {
     premiumOnly: "Premium Only",
     removeLicense: "Remove License",
     confirmRemoveLicense: "Are you sure you want to remove the license?",
+    qualityTiers: {
+      fast: "Fast, lower quality",
+      balanced: "Balanced",
+      good: "Good quality",
+      very_good: "Very good quality",
+      best: "Best quality",
+    },
+    englishOnly: "English only",
+    downloaded: "Downloaded",
+    errors: {
+      ModelNotFound: "Model not found: {model_id}",
+      ModelNotDownloaded: "Model not downloaded: {model_id}",
+      UnsupportedModel: "Unknown model type: {model_id}",
+      ModelLoadFailed: "Failed to load model: {message}",
+      NoModelLoaded: "No model loaded, no transcript.",
+      DownloadFailed: "Download failed: {message}",
+      TranscriptionFailed: "Transcription failed: {message}",
+      Cancelled: "Transcription cancelled",
+      JobNotFound: "Transcription not found",
+      Busy: "Too many transcriptions are waiting, please try again later",
+      LlmUnavailable: "Can't reach the local model server at {url}",
+      AiRequestFailed: "{provider} request failed: {message}",
+      RateLimited: "{provider} rate limit reached, try again in a moment",
+      MissingApiKey: "No API key entered for {provider}",
+      SessionNotFound: "Recording not found, transcribe it first",
+      InvalidReport: "The model didn't return a valid report: {message}",
+      SecretStore: "Keychain error: {message}",
+      LicenseInvalid: "Invalid license key",
+      LicenseEmailMismatch: "Email address does not match the license owner",
+      ActivationLimitReached: "Activation limit reached. Please deactivate your licenses and try again.",
+      LicenseServer: "License activation failed: {message}",
+      NotlokAiFailed: "API Error ({status}): {message}",
+      AlreadyRecording: "Already recording",
+      NotRecording: "Not recording",
+      AlreadyPaused: "Recording is already paused",
+      NotPaused: "Recording is not paused",
+      PermissionDenied: "Permission denied: {permission}",
+      DeviceUnavailable: "Audio device unavailable: {device}",
+      AudioStream: "Audio stream error: {message}",
+      CaptureTimeout: "Audio capture did not start in time",
+      CaptureFailed: "Audio capture failed: {errors}",
+      NoAudioCaptured: "No audio was captured ({sources})",
+      PlatformNotSupported: "This platform is not supported",
+      InvalidLanguage: "Invalid language: {language}. Use tr, en or auto",
+      InvalidConfig: "Invalid setting: {field}",
+      Io: "File error: {message}",
+      Internal: "Unexpected error: {message}",
+    },
   },
 };
 
 type Language = "tr" | "en";
 type Theme = "light" | "dark" | "system";
-type AIProvider = "notlok" | "gemini";
+type AIProvider = "notlok" | "local" | "openai" | "anthropic" | "gemini";
+// Providers the backend talks to, Notlok AI is requested from here
+type BackendProvider = Exclude<AIProvider, "notlok">;
 type PromptTemplate = "meetingNotes" | "meetingSummary" | "actionItems" | "decisionLog" | "custom";
 
+type QualityTier = "fast" | "balanced" | "good" | "very_good" | "best";
+
 interface ModelInfo {
   id: string;
   name: string;
-  size: string;
-  quality: string;
+  size_bytes: number;
+  quality: QualityTier;
+  multilingual: boolean;
   url: string;
+  downloaded: boolean;
+}
+
+interface TranscriptWord {
+  start: number;
+  end: number;
+  text: string;
+  confidence: number; // 0..1
+}
+
+interface TranscriptSegment {
+  start: number;
+  end: number;
+  text: string;
+  speaker?: number | null; // set when diarization is enabled
+  words?: TranscriptWord[]; // empty unless word timestamps are enabled
+}
+
+type TranscriptionTask = "transcribe" | "translate";
+
+interface TranscriptionStarted {
+  job_id: number;
+  task: TranscriptionTask;
+  session_id: string; // the backend saves the transcript under it
+}
+
+interface TranscriptionProgress {
+  job_id: number;
+  task: TranscriptionTask;
+  processed_seconds: number;
+  total_seconds: number;
+}
+
+interface AiEndpoint {
+  base_url: string;
+  model: string | null; // only a local server may pick its own
+}
+
+interface AiConfig {
+  default_provider: BackendProvider;
+  local: AiEndpoint;
+  openai: AiEndpoint;
+  anthropic: AiEndpoint;
+  gemini: AiEndpoint;
+  max_retries: number;
+  max_output_tokens: number;
+}
+
+interface SavedReport {
+  text: string;
+  template: PromptTemplate;
+  provider: BackendProvider;
+  usage: { prompt_tokens: number; completion_tokens: number; estimated: boolean };
+  created_at: number;
+}
+
+interface ActionItem {
+  task: string;
+  owner: string | null;
+  due_date: string | null; // YYYY-MM-DD
+}
+
+// Checked against this shape by the backend before it is stored
+interface SavedMeetingReport {
+  summary: string;
+  decisions: string[];
+  action_items: ActionItem[];
+  open_questions: string[];
+  topics: { title: string; start: number }[]; // seconds into the recording
+  provider: BackendProvider;
+  created_at: number;
+}
+
+interface SessionActionItem extends ActionItem {
+  session_id: string;
+  meeting_started_at: number;
+}
+
+interface ReportStarted {
+  job_id: number;
+  session_id: string;
+  provider: BackendProvider;
+}
+
+interface ReportToken {
+  job_id: number;
+  token: string;
+}
+
+interface Transcript {
+  text: string;
+  segments: TranscriptSegment[];
+  translation?: Transcript; // English version when the task was "translate"
+}
+
+// Backend errors arrive as { code, params }, the message is picked here
+interface NotlokError {
+  code: string;
+  params?: Record<string, unknown>;
+}
+
+interface SourceDiagnostics {
+  source: "microphone" | "system";
+  received_seconds: number;
+  recorded_seconds: number;
+  levels: { average: number; rms: number; peak: number; silence: number };
+}
+
+interface CaptureError {
+  source: "microphone" | "system";
+  error: NotlokError;
 }
 
 interface DownloadProgress {
@@ -332,9 +606,15 @@ interface AudioDevice {
   is_default: boolean;
 }
 
+interface DevicesChangedEvent {
+  inputs: AudioDevice[];
+  outputs: AudioDevice[];
+}
+
+// Filled in by the backend, which keeps the key itself
 interface LicenseInfo {
   valid: boolean;
-  key: string;
+  keyHint: string;
   status: string;
   customerEmail: string;
   activationUsage: number;
@@ -349,6 +629,9 @@ interface RecordingHistory {
   aiReport: string;
   model: string;
   language: string;
+  segments?: TranscriptSegment[];
+  speakers?: Record<number, string>; // names given to speaker ids
+  translation?: string;
 }
 
 interface UpdateInfo {
@@ -366,8 +649,9 @@ interface UpdateInfo {
   changelog?: string;
 }
 
-const EXPECTED_STORE_ID = 53624;
-const EXPECTED_PRODUCT_ID = 699528;
+// Names of the secrets the backend keeps in the OS keychain
+const LICENSE_SECRET = "license-key";
+const apiKeySecret = (provider: BackendProvider) => `${provider}-api-key`;
 
 function App() {
   const [activeTab, setActiveTab] = useState<"main" | "settings" | "license" | "history" | "aireport">("main");
@@ -391,6 +675,8 @@ function App() {
   });
   const [isRecording, setIsRecording] = useState(false);
   const [isStopping, setIsStopping] = useState(false);
+  const [isPaused, setIsPaused] = useState(false);
+  const [captureError, setCaptureError] = useState<CaptureError | null>(null);
 
   // History states
   const [recordingHistory, setRecordingHistory] = useState<RecordingHistory[]>(() => {
@@ -415,6 +701,11 @@ function App() {
   const [selectedModel, setSelectedModel] = useState<string>("");
   const [currentModel, setCurrentModel] = useState<string | null>(null);
   const [transcriptionLanguage, setTranscriptionLanguage] = useState<string>("tr");
+  const [transcriptionProgress, setTranscriptionProgress] = useState<TranscriptionProgress | null>(null);
+  const [transcriptionJobId, setTranscriptionJobId] = useState<number | null>(null);
+  const [transcriptionTask, setTranscriptionTask] = useState<TranscriptionTask>(() => {
+    return localStorage.getItem("notlok-transcription-task") === "translate" ? "translate" : "transcribe";
+  });
   const [downloadProgress, setDownloadProgress] = useState<number | null>(null);
   const [isDownloading, setIsDownloading] = useState(false);
   const [permissions, setPermissions] = useState<PermissionStatus | null>(null);
@@ -446,9 +737,9 @@ function App() {
     const saved = localStorage.getItem("notlok-ai-provider");
     return (saved as AIProvider) || "notlok";
   });
-  const [geminiApiKey, setGeminiApiKey] = useState(() => {
-    return localStorage.getItem("notlok-gemini-key") || "";
-  });
+  // Keys go straight to the keychain, the input only ever holds a new one
+  const [apiKey, setApiKey] = useState("");
+  const [hasApiKey, setHasApiKey] = useState(false);
   const [customPrompt, setCustomPrompt] = useState(() => {
     return localStorage.getItem("notlok-custom-prompt") || "";
   });
@@ -457,6 +748,12 @@ function App() {
     return (saved as PromptTemplate) || "meetingSummary";
   });
   const [aiReport, setAiReport] = useState("");
+  const [aiConfig, setAiConfig] = useState<AiConfig | null>(null);
+  const [reportJobId, setReportJobId] = useState<number | null>(null);
+  const [meetingReport, setMeetingReport] = useState<SavedMeetingReport | null>(null);
+  const [actionItems, setActionItems] = useState<SessionActionItem[]>([]);
+  // Session of the latest transcription, reports are generated from its saved transcript
+  const sessionIdRef = useRef<string | null>(null);
   const [isGenerating, setIsGenerating] = useState(false);
   const [reportError, setReportError] = useState("");
   const [copied, setCopied] = useState(false);
@@ -471,6 +768,57 @@ function App() {
 
   const t = translations[uiLanguage];
 
+  const formatSize = (bytes: number): string => {
+    return bytes >= 1e9 ? `${(bytes / 1e9).toFixed(1)} GB` : `${Math.round(bytes / 1e6)} MB`;
+  };
+
+  const describeModel = (model: ModelInfo): string => {
+    const details = [t.qualityTiers[model.quality]];
+    if (!model.multilingual) details.push(t.englishOnly);
+    if (model.downloaded) details.push(t.downloaded);
+    return `${model.name} - ${formatSize(model.size_bytes)} (${details.join(", ")})`;
+  };
+
+  const formatError = (error: unknown): string => {
+    if (typeof error !== "object" || error === null || !("code" in error)) {
+      return String(error);
+    }
+    const { code, params = {} } = error as NotlokError;
+    const template = (t.errors as Record<string, string>)[code] ?? code;
+    return template.replace(/\{(\w+)\}/g, (_, key: string) => {
+      const value = params[key];
+      if (key === "errors" && Array.isArray(value)) {
+        return (value as CaptureError[]).map(formatCaptureError).join("; ");
+      }
+      if (key === "sources" && Array.isArray(value)) {
+        return (value as SourceDiagnostics[]).map(formatSourceDiagnostics).join("; ");
+      }
+      if (key === "permission") {
+        return value === "microphone" ? t.microphone : t.screenRecording;
+      }
+      if (key === "device" && value == null) {
+        return t.defaultDevice;
+      }
+      return String(value ?? "");
+    });
+  };
+
+  const formatCaptureError = (captureError: CaptureError): string => {
+    const source = captureError.source === "microphone" ? t.captureErrorMicrophone : t.captureErrorSystem;
+    return `${source}: ${formatError(captureError.error)}`;
+  };
+
+  const formatSourceDiagnostics = (diagnostics: SourceDiagnostics): string => {
+    const source = diagnostics.source === "microphone" ? t.microphone : t.systemAudio;
+    if (diagnostics.received_seconds === 0) {
+      return `${source}: ${t.sourceNoSamples}`;
+    }
+    return t.sourceDiagnostics
+      .replace("{source}", source)
+      .replace("{seconds}", diagnostics.received_seconds.toFixed(1))
+      .replace("{peak}", diagnostics.levels.peak.toFixed(3));
+  };
+
   const getStatusText = (statusKey: string) => {
     switch (statusKey) {
       case "ready": return t.ready;
@@ -499,22 +847,16 @@ function App() {
         setAppVersion(version);
 
         // First, check license (fastest, no UI blocking)
-        const savedKey = localStorage.getItem("notlok-license-key");
-        if (savedKey) {
-          setLicenseKey(savedKey);
-          validateLicense(savedKey); // Don't await, runs in background
-          // Check premium status from backend
-          try {
-            const isPremium = await invoke<boolean>("check_premium_license");
-            setHasPremiumLicense(isPremium);
-            if (!isPremium) {
-              // Set premium if we have a saved license key
-              await invoke("set_premium_license", { licenseKey: savedKey });
-              setHasPremiumLicense(true);
-            }
-          } catch (error) {
-            console.error("Error checking premium license:", error);
-          }
+        await migrateSecret(LICENSE_SECRET, "notlok-license-key").catch(console.error);
+        migrateSecret(apiKeySecret("gemini"), "notlok-gemini-key").catch(console.error);
+        const hasLicense = await invoke<boolean>("has_secret", { name: LICENSE_SECRET }).catch((error) => {
+          console.error("Error reading license key:", error);
+          return false;
+        });
+        if (hasLicense) {
+          // The backend counts a saved license as premium until the server says otherwise
+          invoke<boolean>("check_premium_license").then(setHasPremiumLicense).catch(console.error);
+          validateLicense(); // Don't await, runs in background
         } else {
           setIsLicenseChecked(true);
           // Don't force license tab for freemium
@@ -566,6 +908,97 @@ function App() {
     };
   }, []);
 
+  // Backend stops the recording by itself after prolonged silence
+  useEffect(() => {
+    if (!isRecording) return;
+
+    const unlisten = listen("recording-auto-stopped", () => {
+      stopRecording();
+    });
+
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, [isRecording, hasPremiumLicense, currentModel, transcriptionLanguage, recordingHistory]);
+
+  // Pause state can change from the backend (silence) as well as from the button
+  useEffect(() => {
+    const unlistenPaused = listen("recording-paused", () => setIsPaused(true));
+    const unlistenResumed = listen("recording-resumed", () => setIsPaused(false));
+
+    return () => {
+      unlistenPaused.then(fn => fn());
+      unlistenResumed.then(fn => fn());
+    };
+  }, []);
+
+  // Runtime capture failures (unplugged device, stream errors) while recording
+  useEffect(() => {
+    const unlisten = listen<CaptureError>("capture-error", (event) => {
+      console.error("Capture error:", event.payload);
+      setCaptureError(event.payload);
+    });
+
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+
+  // Chunk-by-chunk progress of a running transcription, and its job id for cancelling
+  useEffect(() => {
+    const unlistenStarted = listen<TranscriptionStarted>("transcription-started", (event) => {
+      setTranscriptionJobId(event.payload.job_id);
+      sessionIdRef.current = event.payload.session_id;
+    });
+    const unlistenProgress = listen<TranscriptionProgress>("transcription-progress", (event) => {
+      setTranscriptionProgress(event.payload);
+    });
+
+    return () => {
+      unlistenStarted.then(fn => fn());
+      unlistenProgress.then(fn => fn());
+    };
+  }, []);
+
+  // Reports from the backend providers stream in token by token
+  useEffect(() => {
+    invoke<AiConfig>("get_ai_config")
+      .then(setAiConfig)
+      .catch(console.error);
+
+    const unlistenStarted = listen<ReportStarted>("report-started", (event) => {
+      setReportJobId(event.payload.job_id);
+    });
+    const unlistenToken = listen<ReportToken>("report-token", (event) => {
+      setAiReport(prev => prev + event.payload.token);
+    });
+
+    return () => {
+      unlistenStarted.then(fn => fn());
+      unlistenToken.then(fn => fn());
+    };
+  }, []);
+
+  // Action items of every meeting report, listed under the history
+  useEffect(() => {
+    if (activeTab !== "history") return;
+    invoke<SessionActionItem[]>("list_action_items")
+      .then(setActionItems)
+      .catch(console.error);
+  }, [activeTab]);
+
+  // Device hot-plug, the backend polls the device lists and reports changes
+  useEffect(() => {
+    const unlisten = listen<DevicesChangedEvent>("devices-changed", (event) => {
+      setInputDevices(event.payload.inputs);
+      setOutputDevices(event.payload.outputs);
+    });
+
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+
   // Apply theme and save to localStorage
   useEffect(() => {
     const root = document.documentElement;
@@ -581,20 +1014,27 @@ function App() {
     localStorage.setItem("notlok-theme", theme);
   }, [theme]);
 
-  // Save UI language to localStorage
+  // Save UI language to localStorage and tell the backend, for messages built in Rust
   useEffect(() => {
     localStorage.setItem("notlok-ui-language", uiLanguage);
+    invoke("set_ui_language", { language: uiLanguage }).catch((error) => {
+      console.error("Failed to set UI language:", error);
+    });
   }, [uiLanguage]);
 
   // Save AI settings to localStorage
   useEffect(() => {
     localStorage.setItem("notlok-ai-provider", aiProvider);
+    setApiKey("");
+    if (aiProvider === "notlok" || aiProvider === "local") {
+      setHasApiKey(false);
+      return;
+    }
+    invoke<boolean>("has_secret", { name: apiKeySecret(aiProvider) })
+      .then(setHasApiKey)
+      .catch(console.error);
   }, [aiProvider]);
 
-  useEffect(() => {
-    localStorage.setItem("notlok-gemini-key", geminiApiKey);
-  }, [geminiApiKey]);
-
   useEffect(() => {
     localStorage.setItem("notlok-custom-prompt", customPrompt);
   }, [customPrompt]);
@@ -603,6 +1043,10 @@ function App() {
     localStorage.setItem("notlok-prompt-template", promptTemplate);
   }, [promptTemplate]);
 
+  useEffect(() => {
+    localStorage.setItem("notlok-transcription-task", transcriptionTask);
+  }, [transcriptionTask]);
+
   async function getComputerName(): Promise<string> {
     try {
       // Try to get hostname via Tauri
@@ -627,131 +1071,71 @@ function App() {
 
     try {
       const instanceName = await getComputerName();
-
-      const response = await fetch("https://api.lemonsqueezy.com/v1/licenses/activate", {
-        method: "POST",
-        headers: {
-          "Content-Type": "application/x-www-form-urlencoded",
-        },
-        body: new URLSearchParams({
-          license_key: licenseKey.trim(),
-          instance_name: instanceName,
-        }),
+      // Checked and stored by the backend, the key doesn't come back
+      const info = await invoke<LicenseInfo>("activate_license", {
+        licenseKey: licenseKey.trim(),
+        email: userEmail.trim(),
+        instanceName,
       });
-
-      const data = await response.json();
-
-      if (data.activated) {
-        // Check store_id and product_id
-        if (data.meta.store_id !== EXPECTED_STORE_ID || data.meta.product_id !== EXPECTED_PRODUCT_ID) {
-          setLicenseError(t.invalidLicense);
-          setIsActivating(false);
-          return;
-        }
-
-        // Check if email matches
-        if (data.meta.customer_email.toLowerCase() !== userEmail.trim().toLowerCase()) {
-          setLicenseError(t.emailMismatch);
-          setIsActivating(false);
-          return;
-        }
-
-        const info: LicenseInfo = {
-          valid: true,
-          key: data.license_key.key,
-          status: data.license_key.status,
-          customerEmail: data.meta.customer_email,
-          activationUsage: data.license_key.activation_usage,
-          activationLimit: data.license_key.activation_limit,
-          expiresAt: data.license_key.expires_at,
-        };
-
-        setLicenseInfo(info);
-        localStorage.setItem("notlok-license-key", licenseKey.trim());
-        
-        // Set premium license in backend
-        await invoke("set_premium_license", { licenseKey: licenseKey.trim() });
-        setHasPremiumLicense(true);
-        
-        setActiveTab("main");
-      } else {
-        // Check if activation limit reached
-        if (data.error && data.error.includes("activation")) {
-          setLicenseError(t.activationLimitReached);
-        } else {
-          setLicenseError(data.error || t.activationFailed);
-        }
-      }
+      setLicenseInfo(info);
+      setLicenseKey("");
+      setHasPremiumLicense(true);
+      setActiveTab("main");
     } catch (error) {
-      setLicenseError(t.activationFailed);
+      setLicenseError((error as NotlokError)?.code ? formatError(error) : t.activationFailed);
       console.error("License activation error:", error);
     }
 
     setIsActivating(false);
   }
 
-  async function validateLicense(key: string) {
+  // Checks the saved license, offline it stays valid as long as it is saved
+  async function validateLicense() {
     try {
-      const response = await fetch("https://api.lemonsqueezy.com/v1/licenses/validate", {
-        method: "POST",
-        headers: {
-          "Content-Type": "application/x-www-form-urlencoded",
-        },
-        body: new URLSearchParams({
-          license_key: key,
-        }),
-      });
-
-      const data = await response.json();
-
-      if (data.valid) {
-        // Check store_id and product_id
-        if (data.meta.store_id !== EXPECTED_STORE_ID || data.meta.product_id !== EXPECTED_PRODUCT_ID) {
-          setLicenseInfo(null);
-          localStorage.removeItem("notlok-license-key");
-          setActiveTab("license");
-          setIsLicenseChecked(true);
-          return;
-        }
-
-        const info: LicenseInfo = {
-          valid: true,
-          key: data.license_key.key,
-          status: data.license_key.status,
-          customerEmail: data.meta.customer_email,
-          activationUsage: data.license_key.activation_usage,
-          activationLimit: data.license_key.activation_limit,
-          expiresAt: data.license_key.expires_at,
-        };
-
-        setLicenseInfo(info);
-      } else {
-        setLicenseInfo(null);
-        localStorage.removeItem("notlok-license-key");
+      const info = await invoke<LicenseInfo | null>("validate_license");
+      setLicenseInfo(info);
+      setHasPremiumLicense(info !== null);
+      if (!info) {
         setActiveTab("license");
       }
     } catch (error) {
       console.error("License validation error:", error);
-      // On network error, allow usage if previously validated
-      const savedKey = localStorage.getItem("notlok-license-key");
-      if (savedKey) {
-        setLicenseInfo({
-          valid: true,
-          key: savedKey,
-          status: "active",
-          customerEmail: "",
-          activationUsage: 0,
-          activationLimit: 0,
-          expiresAt: null,
-        });
-      } else {
-        setActiveTab("license");
-      }
     }
 
     setIsLicenseChecked(true);
   }
 
+  // Secrets are kept by the backend in the OS keychain and can't be read back. Values older
+  // versions left in localStorage are moved there.
+  async function migrateSecret(name: string, legacyKey: string) {
+    const legacy = localStorage.getItem(legacyKey);
+    if (legacy) {
+      await invoke("set_secret", { name, value: legacy });
+      localStorage.removeItem(legacyKey);
+    }
+  }
+
+  async function saveApiKey(provider: BackendProvider) {
+    if (!apiKey.trim()) return;
+    try {
+      await invoke("set_secret", { name: apiKeySecret(provider), value: apiKey });
+      setApiKey("");
+      setHasApiKey(true);
+      setReportError("");
+    } catch (error) {
+      setReportError(formatError(error));
+    }
+  }
+
+  async function removeApiKey(provider: BackendProvider) {
+    try {
+      await invoke("delete_secret", { name: apiKeySecret(provider) });
+      setHasApiKey(false);
+    } catch (error) {
+      setReportError(formatError(error));
+    }
+  }
+
   function removeLicense() {
     console.log("removeLicense called - showing dialog");
     setShowRemoveLicenseDialog(true);
@@ -767,9 +1151,8 @@ function App() {
       setLicenseInfo(null);
       setLicenseKey("");
       setUserEmail("");
-      localStorage.removeItem("notlok-license-key");
       
-      // Remove from backend
+      // Remove from backend, which also deletes the saved key
       console.log("Calling backend remove_premium_license...");
       await invoke("remove_premium_license");
       setHasPremiumLicense(false);
@@ -784,28 +1167,10 @@ function App() {
       setLicenseKey("");
       setUserEmail("");
       setHasPremiumLicense(false);
-      localStorage.removeItem("notlok-license-key");
       console.log("Local state cleared despite backend error");
     }
   }
 
-  function getPromptText(): string {
-    switch (promptTemplate) {
-      case "meetingNotes":
-        return t.meetingNotesPrompt;
-      case "meetingSummary":
-        return t.meetingSummaryPrompt;
-      case "actionItems":
-        return t.actionItemsPrompt;
-      case "decisionLog":
-        return t.decisionLogPrompt;
-      case "custom":
-        return customPrompt.trim() || t.meetingSummaryPrompt;
-      default:
-        return t.meetingSummaryPrompt;
-    }
-  }
-
   async function generateAIReport() {
     if (!transcript.trim()) {
       setReportError(t.noTranscript);
@@ -816,63 +1181,39 @@ function App() {
     setReportError("");
     setAiReport("");
 
-    const promptText = getPromptText();
-    const fullPrompt = `${promptText}\n\n--- TRANSCRIPT ---\n${transcript}\n--- END TRANSCRIPT ---`;
-
     logger.log('=== AI Report Generation ===');
+    logger.log('Provider:', aiProvider);
     logger.log('Template:', promptTemplate);
     logger.log('Custom prompt:', customPrompt.trim() || '(empty)');
-    logger.log('Selected prompt text:', promptText.substring(0, 100) + '...');
     logger.log('Transcript length:', transcript.length);
-    logger.log('Full prompt length:', fullPrompt.length);
     logger.log('============================');
 
     try {
-      if (aiProvider === "gemini") {
-        // Use Gemini API
-        if (!geminiApiKey.trim()) {
-          setReportError(t.enterGeminiKey);
+      if (aiProvider !== "notlok") {
+        // Written in the backend from the saved session, tokens arrive through report-token events
+        const sessionId = sessionIdRef.current;
+        if (!sessionId) {
+          setReportError(t.noTranscript);
           setIsGenerating(false);
           return;
         }
-
-        const response = await fetch(
-          `https://generativelanguage.googleapis.com/v1beta/models/gemini-2.0-flash:generateContent`,
-          {
-            method: "POST",
-            headers: {
-              "x-goog-api-key": geminiApiKey.trim(),
-              "Content-Type": "application/json",
-            },
-            body: JSON.stringify({
-              contents: [
-                {
-                  parts: [
-                    {
-                      text: fullPrompt,
-                    },
-                  ],
-                },
-              ],
-            }),
-          }
-        );
-
-        const data = await response.json();
-
-        if (data.candidates && data.candidates[0]?.content?.parts?.[0]?.text) {
-          const report = data.candidates[0].content.parts[0].text;
-          setAiReport(report);
-          // Save to most recent history item
-          if (recordingHistory.length > 0) {
-            updateHistoryWithReport(recordingHistory[0].id, report);
-          }
-        } else {
-          setReportError(data.error?.message || t.reportError);
-        }
+        const report = await invoke<SavedReport>("generate_report", {
+          sessionId,
+          template: promptTemplate,
+          customPrompt,
+          provider: aiProvider,
+        });
+        setAiReport(report.text);
+        updateHistoryWithReport(sessionId, report.text);
       } else {
-        // Use Notlok AI
-        if (!licenseInfo?.key) {
+        // Use Notlok AI, with the same instructions the backend would use
+        const promptText = await invoke<string>("get_report_instructions", {
+          template: promptTemplate,
+          customPrompt,
+        });
+        const fullPrompt = `${promptText}\n\n--- TRANSCRIPT ---\n${transcript}\n--- END TRANSCRIPT ---`;
+
+        if (!licenseInfo?.valid) {
           const errorMsg = uiLanguage === 'tr' 
             ? 'Lisans bilgisi bulunamadı. Lütfen License sekmesinden lisansınızı aktive edin.' 
             : 'License information not found. Please activate your license from License tab.';
@@ -891,40 +1232,14 @@ function App() {
           return;
         }
 
-        // Prepare request with query parameters (API expects GET-style params in URL)
-        const params = new URLSearchParams();
-        params.append('license_key', licenseInfo.key);
-        params.append('email', email);
-        params.append('prompt', fullPrompt);
-        
-        const requestUrl = `https://notlok.app/api/webhook?${params.toString()}`;
-        
         logger.log('=== Notlok AI Request ===');
-        logger.log('License key:', licenseInfo.key);
         logger.log('Email:', email);
         logger.log('Prompt preview:', fullPrompt.substring(0, 200) + '...');
         logger.log('Full prompt length:', fullPrompt.length);
-        logger.log('Request URL length:', requestUrl.length);
         logger.log('========================');
 
-        const response = await fetch(requestUrl, {
-          method: "POST",
-        });
-
-        logger.log('Response status:', response.status, response.ok);
-
-        if (!response.ok) {
-          const errorText = await response.text();
-          logger.error('Error response:', errorText);
-          const errorMsg = uiLanguage === 'tr'
-            ? `API Hatası (${response.status}): ${errorText || response.statusText}`
-            : `API Error (${response.status}): ${errorText || response.statusText}`;
-          setReportError(errorMsg);
-          setIsGenerating(false);
-          return;
-        }
-
-        const data = await response.json();
+        // Sent by the backend, which signs it with the saved license key
+        const data = await invoke<any>("notlok_ai_request", { email, prompt: fullPrompt });
         logger.log('Response data:', data);
         logger.log('Data type:', typeof data);
         logger.log('Is array:', Array.isArray(data));
@@ -995,15 +1310,78 @@ function App() {
       }
     } catch (error) {
       logger.error("AI report error:", error);
+      if (aiProvider !== "notlok" || (error as NotlokError)?.code) {
+        if ((error as NotlokError)?.code !== "Cancelled") {
+          setReportError(formatError(error));
+        }
+        setReportJobId(null);
+        setIsGenerating(false);
+        return;
+      }
       const errorMsg = uiLanguage === 'tr'
         ? `Bağlantı hatası: ${error instanceof Error ? error.message : 'Bilinmeyen hata'}`
         : `Connection error: ${error instanceof Error ? error.message : 'Unknown error'}`;
       setReportError(errorMsg);
     }
 
+    setReportJobId(null);
+    setIsGenerating(false);
+  }
+
+  async function generateMeetingReport() {
+    const sessionId = sessionIdRef.current;
+    if (!transcript.trim() || !sessionId) {
+      setReportError(t.noTranscript);
+      return;
+    }
+
+    setIsGenerating(true);
+    setReportError("");
+    setMeetingReport(null);
+    try {
+      const report = await invoke<SavedMeetingReport>("generate_meeting_report", {
+        sessionId,
+        provider: aiProvider === "notlok" ? null : aiProvider,
+      });
+      setMeetingReport(report);
+    } catch (error) {
+      logger.error("Meeting report error:", error);
+      if ((error as NotlokError)?.code !== "Cancelled") {
+        setReportError(formatError(error));
+      }
+    }
+    setReportJobId(null);
     setIsGenerating(false);
   }
 
+  async function cancelReport() {
+    if (reportJobId === null) return;
+    try {
+      await invoke("cancel_report", { jobId: reportJobId });
+    } catch (error) {
+      console.error(error);
+    }
+  }
+
+  async function saveAiConfig(config: AiConfig) {
+    try {
+      await invoke("set_ai_config", { config });
+      setReportError("");
+    } catch (error) {
+      setReportError(formatError(error));
+    }
+  }
+
+  // Backend providers also become the default for reports requested without one
+  function selectAiProvider(provider: AIProvider) {
+    setAiProvider(provider);
+    if (provider !== "notlok" && aiConfig) {
+      const config = { ...aiConfig, default_provider: provider };
+      setAiConfig(config);
+      saveAiConfig(config);
+    }
+  }
+
   async function copyToClipboard(text: string) {
     try {
       await navigator.clipboard.writeText(text);
@@ -1054,7 +1432,7 @@ function App() {
         }
       } catch (error) {
         console.error("Failed to auto-load model:", error);
-        setStatus(`${t.error} ${error}`);
+        setStatus(`${t.error} ${formatError(error)}`);
         setIsModelLoading(false);
       }
     }, 500); // Small delay to ensure UI is fully rendered first
@@ -1279,7 +1657,7 @@ function App() {
       setStatus(result);
       await loadModels(); // Refresh list
     } catch (error) {
-      setStatus(`${t.error} ${error}`);
+      setStatus(`${t.error} ${formatError(error)}`);
     } finally {
       setIsDownloading(false);
       setDownloadProgress(null);
@@ -1300,7 +1678,7 @@ function App() {
       // Save the loaded model to localStorage
       localStorage.setItem("notlok-last-model", selectedModel);
     } catch (error) {
-      setStatus(`${t.error} ${error}`);
+      setStatus(`${t.error} ${formatError(error)}`);
     } finally {
       setIsModelLoading(false);
     }
@@ -1317,6 +1695,7 @@ function App() {
 
   async function startRecording() {
     try {
+      setCaptureError(null);
       await invoke("start_recording");
       setIsRecording(true);
       setStatus("recording");
@@ -1344,7 +1723,15 @@ function App() {
       (window as any).recordingTimerInterval = timerInterval;
     } catch (error) {
       console.error(error);
-      setStatus(`Error: ${error}`);
+      setStatus(`${t.error} ${formatError(error)}`);
+    }
+  }
+
+  async function togglePause() {
+    try {
+      await invoke(isPaused ? "resume_recording" : "pause_recording");
+    } catch (error) {
+      console.error("Failed to toggle pause:", error);
     }
   }
 
@@ -1354,6 +1741,7 @@ function App() {
     try {
       setIsStopping(true);
       setIsRecording(false);
+      setIsPaused(false);
       
       // Clear recording timer
       if ((window as any).recordingTimerInterval) {
@@ -1368,25 +1756,31 @@ function App() {
       // UI'ı serbest bırak - transkripsiyon arka planda çalışacak
       setIsStopping(false);
       setStatus("processing"); // İşleniyor göster
+      setTranscriptionProgress(null);
       
       // Transkripsiyon işlemini arka planda başlat
       // UI donmadan çalışacak
       setTimeout(async () => {
         try {
-          const result = await invoke<string>("transcribe_audio");
+          const { text: result, segments, translation } = await invoke<Transcript>("transcribe_audio", {
+            task: transcriptionTask,
+          });
           
           setStatus("stopped");
-          setTranscript(result);
+          setTranscript(translation ? `${result}\n\n${translation.text}` : result);
 
           // Save to history (only for premium users)
           if (result && result.trim() && hasPremiumLicense) {
             const newRecord: RecordingHistory = {
-              id: Date.now().toString(),
+              id: sessionIdRef.current ?? Date.now().toString(),
               date: new Date().toISOString(),
               transcript: result,
               aiReport: "",
               model: currentModel || "",
               language: transcriptionLanguage,
+              segments,
+              speakers: {},
+              translation: translation?.text,
             };
             const updatedHistory = [newRecord, ...recordingHistory];
             setRecordingHistory(updatedHistory);
@@ -1394,15 +1788,20 @@ function App() {
           }
         } catch (error) {
           console.error(error);
-          setStatus(`${t.error} ${error}`);
+          if ((error as NotlokError)?.code === "Cancelled") {
+            setStatus("stopped");
+          } else {
+            setStatus(`${t.error} ${formatError(error)}`);
+          }
         }
         
+        setTranscriptionJobId(null);
         setRecordingStartTime(null);
       }, 100); // Küçük delay ile UI'ı serbest bırak
       
     } catch (error) {
       console.error(error);
-      setStatus(`${t.error} ${error}`);
+      setStatus(`${t.error} ${formatError(error)}`);
       setIsStopping(false);
     }
   }
@@ -1416,7 +1815,40 @@ function App() {
     localStorage.setItem("notlok-history", JSON.stringify(updatedHistory));
   }
 
+  async function cancelTranscription() {
+    if (transcriptionJobId === null) return;
+    try {
+      await invoke("cancel_transcription", { jobId: transcriptionJobId });
+    } catch (error) {
+      console.error(error);
+    }
+  }
+
+  // The backend keeps the names with the session so reports use them; an empty name removes it
+  function renameSpeaker(recordId: string, speaker: number, name: string) {
+    invoke("rename_speaker", { sessionId: recordId, speaker, name }).catch(console.error);
+    const updatedHistory = recordingHistory.map(item => {
+      if (item.id !== recordId) return item;
+      const speakers = { ...item.speakers };
+      if (name) {
+        speakers[speaker] = name;
+      } else {
+        delete speakers[speaker];
+      }
+      return { ...item, speakers };
+    });
+    setRecordingHistory(updatedHistory);
+    localStorage.setItem("notlok-history", JSON.stringify(updatedHistory));
+    setSelectedHistoryItem(updatedHistory.find(item => item.id === recordId) || null);
+  }
+
+  function speakerName(item: RecordingHistory, speaker: number) {
+    return item.speakers?.[speaker] || `${t.speaker} ${speaker + 1}`;
+  }
+
   function deleteHistoryItem(id: string) {
+    invoke("delete_session", { sessionId: id }).catch(console.error);
+    setActionItems(items => items.filter(item => item.session_id !== id));
     const updatedHistory = recordingHistory.filter(item => item.id !== id);
     setRecordingHistory(updatedHistory);
     localStorage.setItem("notlok-history", JSON.stringify(updatedHistory));
@@ -1427,7 +1859,11 @@ function App() {
 
   function clearHistory() {
     if (window.confirm(t.confirmClear)) {
+      for (const item of recordingHistory) {
+        invoke("delete_session", { sessionId: item.id }).catch(console.error);
+      }
       setRecordingHistory([]);
+      setActionItems([]);
       localStorage.removeItem("notlok-history");
       setSelectedHistoryItem(null);
     }
@@ -1598,7 +2034,7 @@ function App() {
               >
                 {models.map((model) => (
                   <option key={model.id} value={model.id}>
-                    {model.name} - {model.size} ({model.quality})
+                    {describeModel(model)}
                   </option>
                 ))}
               </select>
@@ -1651,13 +2087,32 @@ function App() {
                 {!currentModel ? t.loadModelFirst : status === "processing" ? t.processing : t.startRecording}
               </button>
             ) : (
-              <button 
-                onClick={stopRecording} 
-                className="btn stop"
-                disabled={isStopping}
-              >
-                {isStopping ? t.stoppingRecording : t.stopRecording}
-              </button>
+              <>
+                <button 
+                  onClick={stopRecording} 
+                  className="btn stop"
+                  disabled={isStopping}
+                >
+                  {isStopping ? t.stoppingRecording : t.stopRecording}
+                </button>
+                {!isStopping && (
+                  <button onClick={togglePause} className="btn secondary">
+                    {isPaused ? t.resumeRecording : t.pauseRecording}
+                  </button>
+                )}
+              </>
+            )}
+
+            {isRecording && isPaused && (
+              <div className="recording-timer">
+                <span className="timer-label">⏸️ {t.recordingPaused}</span>
+              </div>
+            )}
+
+            {isRecording && captureError && (
+              <div className="recording-timer">
+                <span className="timer-hint">⚠️ {formatCaptureError(captureError)}</span>
+              </div>
             )}
             
             {/* Recording Timer (Free Tier Countdown) */}
@@ -1687,6 +2142,17 @@ function App() {
             <div className="loading-screen" style={{ padding: '20px', background: 'var(--card-bg)', borderRadius: '8px', marginTop: '1rem' }}>
               <div className="loading-spinner"></div>
               <p>{status === "processing" ? t.processing : t.stoppingRecording}</p>
+              {status === "processing" && transcriptionProgress && transcriptionProgress.total_seconds > 0 && (
+                <p>
+                  {transcriptionProgress.task === "translate" ? t.translating : t.transcribing}{" "}
+                  {Math.round((transcriptionProgress.processed_seconds / transcriptionProgress.total_seconds) * 100)}%
+                </p>
+              )}
+              {status === "processing" && transcriptionJobId !== null && (
+                <button onClick={cancelTranscription} className="btn small secondary">
+                  {t.cancelTranscription}
+                </button>
+              )}
               {status === "processing" && (
                 <p style={{ fontSize: '0.9rem', opacity: 0.7, marginTop: '0.5rem' }}>
                   {uiLanguage === 'tr' 
@@ -1765,14 +2231,69 @@ function App() {
               <label>{t.aiProvider}:</label>
               <select
                 value={aiProvider}
-                onChange={(e) => setAiProvider(e.target.value as AIProvider)}
+                onChange={(e) => selectAiProvider(e.target.value as AIProvider)}
                 disabled={isGenerating}
               >
                 <option value="notlok">{t.notlokAI}</option>
+                <option value="local">{t.localAI}</option>
+                <option value="openai">{t.openaiAI}</option>
+                <option value="anthropic">{t.anthropicAI}</option>
                 <option value="gemini">{t.geminiAI}</option>
               </select>
             </div>
 
+            {aiProvider !== "notlok" && aiConfig && (() => {
+              const endpoint = aiConfig[aiProvider];
+              const setEndpoint = (changes: Partial<AiEndpoint>) =>
+                setAiConfig({ ...aiConfig, [aiProvider]: { ...endpoint, ...changes } });
+              return (
+                <>
+                  <div className="setting-group">
+                    <label>{t.serverUrl}:</label>
+                    <input
+                      type="text"
+                      value={endpoint.base_url}
+                      onChange={(e) => setEndpoint({ base_url: e.target.value })}
+                      onBlur={() => saveAiConfig(aiConfig)}
+                      disabled={isGenerating}
+                    />
+                  </div>
+                  <div className="setting-group">
+                    <label>{t.modelName}:</label>
+                    <input
+                      type="text"
+                      value={endpoint.model ?? ""}
+                      onChange={(e) => setEndpoint({ model: e.target.value || null })}
+                      onBlur={() => saveAiConfig(aiConfig)}
+                      placeholder={aiProvider === "local" ? t.localModelPlaceholder : undefined}
+                      disabled={isGenerating}
+                    />
+                  </div>
+                </>
+              );
+            })()}
+
+            {(aiProvider === "openai" || aiProvider === "anthropic" || aiProvider === "gemini") && (
+              <div className="setting-group">
+                <label>{t.apiKey}:</label>
+                <input
+                  type="password"
+                  value={apiKey}
+                  onChange={(e) => setApiKey(e.target.value)}
+                  onBlur={() => saveApiKey(aiProvider)}
+                  placeholder={hasApiKey ? t.apiKeySaved : t.enterApiKey}
+                  className="api-key-input"
+                  style={{ flex: 1 }}
+                  disabled={isGenerating}
+                />
+                {hasApiKey && (
+                  <button onClick={() => removeApiKey(aiProvider)} className="btn small secondary" disabled={isGenerating}>
+                    {t.removeApiKey}
+                  </button>
+                )}
+              </div>
+            )}
+
             <div className="setting-group">
               <label>{t.promptTemplate}:</label>
               <select
@@ -1811,6 +2332,20 @@ function App() {
             >
               {isGenerating ? t.generating : t.generateReport}
             </button>
+            {aiProvider !== "notlok" && (
+              <button
+                onClick={generateMeetingReport}
+                disabled={isGenerating || !transcript.trim()}
+                className="btn secondary"
+              >
+                {t.extractMeetingReport}
+              </button>
+            )}
+            {isGenerating && aiProvider !== "notlok" && reportJobId !== null && (
+              <button onClick={cancelReport} className="btn small secondary">
+                {t.stopGenerating}
+              </button>
+            )}
             {aiProvider === "notlok" && (
               <span className="estimated-time">{t.estimatedTime}</span>
             )}
@@ -1837,6 +2372,60 @@ function App() {
             </div>
           )}
 
+          {meetingReport && (
+            <div className="report-result">
+              <div className="report-header">
+                <h3>{t.meetingReportTitle}</h3>
+              </div>
+              <div className="report-content">
+                <h4>{t.reportSummary}</h4>
+                <p>{meetingReport.summary}</p>
+                {meetingReport.decisions.length > 0 && (
+                  <>
+                    <h4>{t.decisions}</h4>
+                    <ul>
+                      {meetingReport.decisions.map((decision, i) => <li key={i}>{decision}</li>)}
+                    </ul>
+                  </>
+                )}
+                {meetingReport.action_items.length > 0 && (
+                  <>
+                    <h4>{t.actionItems}</h4>
+                    <ul>
+                      {meetingReport.action_items.map((item, i) => (
+                        <li key={i}>
+                          {item.task}
+                          {item.owner && <> · {t.owner}: {item.owner}</>}
+                          {item.due_date && <> · {t.dueDate}: {item.due_date}</>}
+                        </li>
+                      ))}
+                    </ul>
+                  </>
+                )}
+                {meetingReport.open_questions.length > 0 && (
+                  <>
+                    <h4>{t.openQuestions}</h4>
+                    <ul>
+                      {meetingReport.open_questions.map((question, i) => <li key={i}>{question}</li>)}
+                    </ul>
+                  </>
+                )}
+                {meetingReport.topics.length > 0 && (
+                  <>
+                    <h4>{t.topics}</h4>
+                    <ul>
+                      {meetingReport.topics.map((topic, i) => (
+                        <li key={i}>
+                          [{Math.floor(topic.start / 60)}:{String(Math.floor(topic.start % 60)).padStart(2, "0")}] {topic.title}
+                        </li>
+                      ))}
+                    </ul>
+                  </>
+                )}
+              </div>
+            </div>
+          )}
+
           {aiUsage && aiProvider === "notlok" && (
             <div className="settings-panel ai-usage-panel">
               <h3>{t.aiUsageTitle}</h3>
@@ -1918,15 +2507,15 @@ function App() {
             </div>
 
             <div className="setting-group">
-              <label>{t.geminiApiKey}:</label>
-              <input
-                type="password"
-                value={geminiApiKey}
-                onChange={(e) => setGeminiApiKey(e.target.value)}
-                placeholder={t.enterGeminiKey}
-                className="api-key-input"
-                style={{ flex: 1 }}
-              />
+              <label>{t.transcriptionTask}:</label>
+              <select
+                value={transcriptionTask}
+                onChange={(e) => setTranscriptionTask(e.target.value as TranscriptionTask)}
+                disabled={isRecording}
+              >
+                <option value="transcribe">{t.taskTranscribe}</option>
+                <option value="translate">{t.taskTranslate}</option>
+              </select>
             </div>
           </div>
 
@@ -2073,7 +2662,7 @@ function App() {
                 </div>
                 <div className="license-detail">
                   <label>{t.licenseKey}:</label>
-                  <span>{licenseInfo.key.substring(0, 8)}...{licenseInfo.key.substring(licenseInfo.key.length - 4)}</span>
+                  <span>{licenseInfo.keyHint}</span>
                 </div>
                 {licenseInfo.customerEmail && (
                   <div className="license-detail">
@@ -2216,6 +2805,7 @@ function App() {
                       onClick={async () => {
                         // Set transcript and generate report
                         setTranscript(selectedHistoryItem.transcript);
+                        sessionIdRef.current = selectedHistoryItem.id;
                         setActiveTab('aireport');
                         // Auto-generate report
                         setTimeout(() => {
@@ -2231,11 +2821,48 @@ function App() {
                     </button>
                   )}
                 </div>
-                <div className="history-content">
-                  {selectedHistoryItem.transcript}
-                </div>
+                {selectedHistoryItem.segments?.some(s => s.speaker != null) ? (
+                  <>
+                    <div className="speaker-names">
+                      {[...new Set(selectedHistoryItem.segments.map(s => s.speaker).filter((s): s is number => s != null))].map(speaker => (
+                        <label key={`${selectedHistoryItem.id}-${speaker}`} className="speaker-name">
+                          {t.speaker} {speaker + 1}
+                          <input
+                            type="text"
+                            defaultValue={selectedHistoryItem.speakers?.[speaker] || ""}
+                            placeholder={t.renameSpeaker}
+                            onBlur={(e) => renameSpeaker(selectedHistoryItem.id, speaker, e.target.value.trim())}
+                          />
+                        </label>
+                      ))}
+                    </div>
+                    <div className="history-content">
+                      {selectedHistoryItem.segments.map((segment, i) => (
+                        <p key={i} className="speaker-segment">
+                          {segment.speaker != null && (
+                            <strong>{speakerName(selectedHistoryItem, segment.speaker)}: </strong>
+                          )}
+                          {segment.text.trim()}
+                        </p>
+                      ))}
+                    </div>
+                  </>
+                ) : (
+                  <div className="history-content">
+                    {selectedHistoryItem.transcript}
+                  </div>
+                )}
               </div>
 
+              {selectedHistoryItem.translation && (
+                <div className="history-section">
+                  <h4>{t.translation}</h4>
+                  <div className="history-content">
+                    {selectedHistoryItem.translation}
+                  </div>
+                </div>
+              )}
+
               {selectedHistoryItem.aiReport && (
                 <div className="history-section">
                   <h4>{t.aiReport}</h4>
@@ -2288,6 +2915,7 @@ function App() {
                             onClick={() => {
                               // Set transcript and navigate to AI Report
                               setTranscript(item.transcript);
+                              sessionIdRef.current = item.id;
                               setActiveTab('aireport');
                             }}
                             className="btn small ai-report-btn-small"
@@ -2306,6 +2934,22 @@ function App() {
                   ))}
                 </div>
               )}
+
+              {actionItems.length > 0 && (
+                <div className="history-section">
+                  <h4>{t.allActionItems}</h4>
+                  <ul>
+                    {actionItems.map((item, i) => (
+                      <li key={`${item.session_id}-${i}`}>
+                        {item.task}
+                        {item.owner && <> · {t.owner}: {item.owner}</>}
+                        {item.due_date && <> · {t.dueDate}: {item.due_date}</>}
+                        {" "}({new Date(item.meeting_started_at).toLocaleDateString()})
+                      </li>
+                    ))}
+                  </ul>
+                </div>
+              )}
             </div>
           )}
         </>